@@ -0,0 +1,639 @@
+//! `#[derive(Encodable)]`, which implements serde's `Serialize` and `Deserialize` for a struct
+//! while letting each field choose how it's encoded, and `#[derive(EncodedShape)]`, which
+//! computes the lengths of a type's encoding at compile time. See the `encodable` crate for the
+//! attributes they accept.
+
+use proc_macro2::{Literal, Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, parse_quote, Data, DeriveInput, Expr, ExprLit, Fields, GenericParam,
+    Generics, Ident, Index, Lit, LitStr, Member, Type,
+};
+
+/// Derives `Serialize` and `Deserialize` for a struct, replacing serde's derives. Structs with
+/// named fields can be read from sequences, as the encodable deserializer gives them, or from
+/// maps, as formats like JSON give them.
+///
+/// Fields accept `#[encodable(...)]` attributes:
+/// - `skip`: the field isn't encoded, and is set to its default value when decoded.
+/// - `one_hot`: enums inside of the field are stored as one-hot bools.
+/// - `scale = k`: the number is stored as a float multiplied by `k`. Reading back a float that
+///   is out of the field's range is an error.
+/// - `lane = "f"`: every value inside of the field is stored in the `f`, `i` or `b` lane.
+#[proc_macro_derive(Encodable, attributes(encodable))]
+pub fn derive_encodable(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives `EncodedShape` for a struct or an enum, following the `#[encodable(...)]` attributes
+/// of a struct's fields. Every field must implement `EncodedShape` itself.
+#[proc_macro_derive(EncodedShape, attributes(encodable))]
+pub fn derive_encoded_shape(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_encoded_shape(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// How a field is encoded, as set by its attributes.
+#[derive(Default)]
+struct Attrs {
+    skip: bool,
+    one_hot: bool,
+    scale: Option<f64>,
+    lane: Option<char>,
+}
+
+/// A field of the struct being derived.
+struct Field {
+    member: Member,
+    /// The name the field is serialized with.
+    name: String,
+    ty: Type,
+    attrs: Attrs,
+}
+
+impl Attrs {
+    fn parse(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+        let mut parsed = Self::default();
+        for attr in attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("encodable"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    parsed.skip = true;
+                } else if meta.path.is_ident("one_hot") {
+                    parsed.one_hot = true;
+                } else if meta.path.is_ident("scale") {
+                    let scale = scale_factor(&meta.value()?.parse()?)?;
+                    parsed.scale = Some(scale);
+                } else if meta.path.is_ident("lane") {
+                    let lane: LitStr = meta.value()?.parse()?;
+                    parsed.lane = match lane.value().as_str() {
+                        "f" => Some('f'),
+                        "i" => Some('i'),
+                        "b" => Some('b'),
+                        _ => {
+                            return Err(syn::Error::new(
+                                lane.span(),
+                                "expected one of the lanes \"f\", \"i\" or \"b\"",
+                            ))
+                        }
+                    };
+                } else {
+                    return Err(meta.error("expected one of skip, one_hot, scale or lane"));
+                }
+                Ok(())
+            })?;
+        }
+
+        if parsed.skip && (parsed.one_hot || parsed.scale.is_some() || parsed.lane.is_some()) {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "a skipped field can't have other encodable attributes",
+            ));
+        }
+        if parsed.one_hot && parsed.scale.is_some() {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "a scaled number has no enums to store as one-hot",
+            ));
+        }
+        Ok(parsed)
+    }
+}
+
+/// Reads the factor of `scale = k`, which must be a non-zero number literal.
+fn scale_factor(expr: &Expr) -> syn::Result<f64> {
+    let (negative, lit) = match expr {
+        Expr::Unary(syn::ExprUnary {
+            op: syn::UnOp::Neg(_),
+            expr,
+            ..
+        }) => (true, &**expr),
+        expr => (false, expr),
+    };
+    let scale = match lit {
+        Expr::Lit(ExprLit {
+            lit: Lit::Float(f), ..
+        }) => f.base10_parse::<f64>()?,
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(i), ..
+        }) => i.base10_parse::<f64>()?,
+        _ => return Err(syn::Error::new_spanned(expr, "expected a number")),
+    };
+    if scale == 0.0 || !scale.is_finite() {
+        return Err(syn::Error::new_spanned(
+            expr,
+            "the scale must be a finite, non-zero number",
+        ));
+    }
+    Ok(if negative { -scale } else { scale })
+}
+
+impl Field {
+    /// The expression that serializes the field, with its attributes' wrappers applied.
+    fn serialized(&self) -> TokenStream {
+        let member = &self.member;
+        let mut value = quote!(&self.#member);
+        if let Some(scale) = self.attrs.scale {
+            let scale = Literal::f64_suffixed(scale);
+            value = quote!(::encodable::attrs::Scaled(#value, #scale));
+        }
+        if self.attrs.one_hot {
+            value = quote!(::encodable::attrs::OneHot(#value));
+        }
+        if let Some(lane) = self.attrs.lane {
+            value = quote!(::encodable::attrs::InLane::<_, #lane>(#value));
+        }
+        quote!(&#value)
+    }
+
+    /// The type the field is deserialized as, with its attributes' wrappers applied.
+    fn wire_type(&self) -> TokenStream {
+        let mut ty = match self.attrs.scale {
+            Some(_) => quote!(f64),
+            None => {
+                let ty = &self.ty;
+                quote!(#ty)
+            }
+        };
+        if self.attrs.one_hot {
+            ty = quote!(::encodable::attrs::OneHot<#ty>);
+        }
+        if let Some(lane) = self.attrs.lane {
+            ty = quote!(::encodable::attrs::InLane<#ty, #lane>);
+        }
+        ty
+    }
+
+    /// Converts `value`, of the field's wire type, back into the field's type.
+    fn unwrapped(&self, value: TokenStream) -> TokenStream {
+        let mut value = value;
+        if self.attrs.lane.is_some() {
+            value = quote!(#value.0);
+        }
+        if self.attrs.one_hot {
+            value = quote!(#value.0);
+        }
+        if let Some(scale) = self.attrs.scale {
+            let ty = &self.ty;
+            let scale = Literal::f64_suffixed(scale);
+            value = quote! {
+                match ::encodable::attrs::unscale::<#ty, _>(#value, #scale) {
+                    ::core::result::Result::Ok(value) => value,
+                    ::core::result::Result::Err(error) => return ::core::result::Result::Err(error),
+                }
+            };
+        }
+        value
+    }
+
+    /// The lengths of the field's lanes, with enums stored as one-hot bools if `one_hot` is set.
+    fn lens(&self, one_hot: bool) -> TokenStream {
+        let ty = &self.ty;
+        let mut lens = if self.attrs.skip {
+            quote!([0, 0, 0])
+        } else if self.attrs.scale.is_some() {
+            quote!([1, 0, 0])
+        } else if one_hot || self.attrs.one_hot {
+            quote!(<#ty as ::encodable::encoded_shape::EncodedShape>::ONE_HOT_LENS)
+        } else {
+            quote!(::encodable::encoded_shape::lens::<#ty>())
+        };
+        if let Some(lane) = self.attrs.lane {
+            lens = quote!(::encodable::encoded_shape::in_lane(#lens, #lane));
+        }
+        lens
+    }
+}
+
+/// Reads the fields of a struct or of an enum's variant.
+fn parse_fields(fields: &Fields) -> syn::Result<Vec<Field>> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let (member, name) = match &field.ident {
+                Some(ident) => (Member::Named(ident.clone()), ident.to_string()),
+                None => (Member::Unnamed(Index::from(i)), i.to_string()),
+            };
+            Ok(Field {
+                member,
+                name,
+                ty: field.ty.clone(),
+                attrs: Attrs::parse(&field.attrs)?,
+            })
+        })
+        .collect()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let data = match &input.data {
+        Data::Struct(data) => data,
+        Data::Enum(data) => {
+            return Err(syn::Error::new_spanned(
+                data.enum_token,
+                "Encodable can only be derived for structs, derive serde's traits for enums",
+            ))
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new_spanned(
+                data.union_token,
+                "Encodable can only be derived for structs",
+            ))
+        }
+    };
+
+    let fields = parse_fields(&data.fields)?;
+    let serialize = expand_serialize(input, &data.fields, &fields);
+    let deserialize = expand_deserialize(input, &data.fields, &fields);
+    Ok(quote! {
+        #serialize
+        #deserialize
+    })
+}
+
+fn expand_encoded_shape(input: &DeriveInput) -> syn::Result<TokenStream> {
+    // the lengths of some fields put one after the other
+    let sum = |fields: &[Field], one_hot: bool| {
+        let lens = fields.iter().map(|field| field.lens(one_hot));
+        quote! {{
+            let lens = [0, 0, 0];
+            #(let lens = ::encodable::encoded_shape::add(lens, #lens);)*
+            lens
+        }}
+    };
+
+    let (lens, one_hot_lens) = match &input.data {
+        Data::Struct(data) => {
+            let fields = parse_fields(&data.fields)?;
+            (sum(&fields, false), sum(&fields, true))
+        }
+        Data::Enum(data) => {
+            let mut payloads = Vec::new();
+            for variant in &data.variants {
+                let fields = parse_fields(&variant.fields)?;
+                if let Some(field) = variant.fields.iter().find(|field| {
+                    field
+                        .attrs
+                        .iter()
+                        .any(|attr| attr.path().is_ident("encodable"))
+                }) {
+                    return Err(syn::Error::new_spanned(
+                        field,
+                        "the fields of enums can't have encodable attributes",
+                    ));
+                }
+                payloads.push(fields);
+            }
+            let variants = data.variants.len();
+            // the discriminant, followed by a region as wide as the widest payload
+            let union = |one_hot: bool| {
+                let payloads = payloads.iter().map(|fields| sum(fields, one_hot));
+                quote! {{
+                    let union = [0, 0, 0];
+                    #(let union = ::encodable::encoded_shape::max(union, #payloads);)*
+                    union
+                }}
+            };
+            let (union, one_hot_union) = (union(false), union(true));
+            (
+                quote!(::encodable::encoded_shape::add([0, 1, 0], #union)),
+                quote!(::encodable::encoded_shape::add([0, 0, #variants], #one_hot_union)),
+            )
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new_spanned(
+                data.union_token,
+                "EncodedShape can only be derived for structs and enums",
+            ))
+        }
+    };
+
+    let ident = &input.ident;
+    let generics = bounded(
+        &input.generics,
+        parse_quote!(::encodable::encoded_shape::EncodedShape),
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::encodable::encoded_shape::EncodedShape for #ident #ty_generics
+        #where_clause
+        {
+            const F_LEN: usize = (#lens)[0];
+            const I_LEN: usize = (#lens)[1];
+            const B_LEN: usize = (#lens)[2];
+            const ONE_HOT_LENS: [usize; 3] = #one_hot_lens;
+        }
+    })
+}
+
+/// Adds `bound` to every type parameter of `generics`.
+fn bounded(generics: &Generics, bound: syn::TypeParamBound) -> Generics {
+    let mut generics = generics.clone();
+    for param in &mut generics.params {
+        if let GenericParam::Type(param) = param {
+            param.bounds.push(bound.clone());
+        }
+    }
+    generics
+}
+
+fn expand_serialize(input: &DeriveInput, kind: &Fields, fields: &[Field]) -> TokenStream {
+    let ident = &input.ident;
+    let name = ident.to_string();
+    let generics = bounded(
+        &input.generics,
+        parse_quote!(::encodable::attrs::serde::Serialize),
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let encoded: Vec<&Field> = fields.iter().filter(|field| !field.attrs.skip).collect();
+    let len = encoded.len();
+
+    let body = match kind {
+        Fields::Named(_) => {
+            let names = encoded.iter().map(|field| &field.name);
+            let values = encoded.iter().map(|field| field.serialized());
+            quote! {
+                use ::encodable::attrs::serde::ser::SerializeStruct as _;
+                let mut state = serializer.serialize_struct(#name, #len)?;
+                #(state.serialize_field(#names, #values)?;)*
+                state.end()
+            }
+        }
+        Fields::Unnamed(_) if fields.len() == 1 && len == 1 => {
+            let value = encoded[0].serialized();
+            quote!(serializer.serialize_newtype_struct(#name, #value))
+        }
+        Fields::Unnamed(_) => {
+            let values = encoded.iter().map(|field| field.serialized());
+            quote! {
+                use ::encodable::attrs::serde::ser::SerializeTupleStruct as _;
+                let mut state = serializer.serialize_tuple_struct(#name, #len)?;
+                #(state.serialize_field(#values)?;)*
+                state.end()
+            }
+        }
+        Fields::Unit => quote!(serializer.serialize_unit_struct(#name)),
+    };
+
+    quote! {
+        impl #impl_generics ::encodable::attrs::serde::Serialize for #ident #ty_generics
+        #where_clause
+        {
+            fn serialize<__S>(&self, serializer: __S) -> ::core::result::Result<__S::Ok, __S::Error>
+            where
+                __S: ::encodable::attrs::serde::Serializer,
+            {
+                #body
+            }
+        }
+    }
+}
+
+fn expand_deserialize(input: &DeriveInput, kind: &Fields, fields: &[Field]) -> TokenStream {
+    let ident = &input.ident;
+    let name = ident.to_string();
+    let expecting = format!("struct {name}");
+    let generics = bounded(
+        &input.generics,
+        parse_quote!(::encodable::attrs::serde::Deserialize<'de>),
+    );
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    let mut de_generics = generics.clone();
+    de_generics.params.insert(0, parse_quote!('de));
+    let (de_impl_generics, visitor_generics, de_where_clause) = de_generics.split_for_impl();
+    let marker_params = input.generics.params.iter().map(|param| match param {
+        GenericParam::Type(param) => {
+            let ident = &param.ident;
+            quote!(#ident)
+        }
+        GenericParam::Lifetime(param) => {
+            let lifetime = &param.lifetime;
+            quote!(&#lifetime ())
+        }
+        GenericParam::Const(param) => {
+            let ident = &param.ident;
+            quote!([(); #ident])
+        }
+    });
+
+    let encoded: Vec<&Field> = fields.iter().filter(|field| !field.attrs.skip).collect();
+    let len = encoded.len();
+    let bindings: Vec<_> = (0..fields.len())
+        .map(|i| format_ident!("__field{}", i))
+        .collect();
+    let reads = fields
+        .iter()
+        .zip(&bindings)
+        .filter(|(field, _)| !field.attrs.skip)
+        .enumerate()
+        .map(|(i, (field, binding))| {
+            let wire = field.wire_type();
+            let value = field.unwrapped(quote!(value));
+            quote! {
+                let #binding = match ::encodable::attrs::serde::de::SeqAccess::next_element::<#wire>(
+                    &mut seq,
+                )? {
+                    ::core::option::Option::Some(value) => #value,
+                    ::core::option::Option::None => {
+                        return ::core::result::Result::Err(
+                            ::encodable::attrs::serde::de::Error::invalid_length(#i, &self),
+                        )
+                    }
+                };
+            }
+        });
+    let defaults = fields
+        .iter()
+        .zip(&bindings)
+        .filter(|(field, _)| field.attrs.skip)
+        .map(|(_, binding)| quote!(let #binding = ::core::default::Default::default();));
+    let members = fields.iter().map(|field| &field.member);
+    let constructed = quote!(#ident { #(#members: #bindings),* });
+
+    let visit_seq = quote! {
+        fn visit_seq<__A>(self, mut seq: __A) -> ::core::result::Result<Self::Value, __A::Error>
+        where
+            __A: ::encodable::attrs::serde::de::SeqAccess<'de>,
+        {
+            #(#reads)*
+            #(#defaults)*
+            ::core::result::Result::Ok(#constructed)
+        }
+    };
+    let (visit, call) = match kind {
+        Fields::Named(_) => {
+            let visit_map = expand_visit_map(fields, &bindings, &constructed);
+            let names = encoded.iter().map(|field| &field.name);
+            (
+                quote! {
+                    #visit_seq
+                    #visit_map
+                },
+                quote! {
+                    deserializer.deserialize_struct(#name, &[#(#names),*], __Visitor(::core::marker::PhantomData))
+                },
+            )
+        }
+        Fields::Unnamed(_) if fields.len() == 1 && len == 1 => {
+            let wire = encoded[0].wire_type();
+            let value = encoded[0].unwrapped(quote!(value));
+            (
+                quote! {
+                    fn visit_newtype_struct<__D>(
+                        self,
+                        deserializer: __D,
+                    ) -> ::core::result::Result<Self::Value, __D::Error>
+                    where
+                        __D: ::encodable::attrs::serde::Deserializer<'de>,
+                    {
+                        let value = <#wire as ::encodable::attrs::serde::Deserialize>::deserialize(
+                            deserializer,
+                        )?;
+                        ::core::result::Result::Ok(#ident(#value))
+                    }
+                },
+                quote! {
+                    deserializer.deserialize_newtype_struct(#name, __Visitor(::core::marker::PhantomData))
+                },
+            )
+        }
+        Fields::Unnamed(_) => (
+            visit_seq,
+            quote! {
+                deserializer.deserialize_tuple_struct(#name, #len, __Visitor(::core::marker::PhantomData))
+            },
+        ),
+        Fields::Unit => (
+            quote! {
+                fn visit_unit<__E>(self) -> ::core::result::Result<Self::Value, __E>
+                where
+                    __E: ::encodable::attrs::serde::de::Error,
+                {
+                    ::core::result::Result::Ok(#ident)
+                }
+            },
+            quote! {
+                deserializer.deserialize_unit_struct(#name, __Visitor(::core::marker::PhantomData))
+            },
+        ),
+    };
+
+    quote! {
+        impl #de_impl_generics ::encodable::attrs::serde::Deserialize<'de> for #ident #ty_generics
+        #de_where_clause
+        {
+            fn deserialize<__D>(deserializer: __D) -> ::core::result::Result<Self, __D::Error>
+            where
+                __D: ::encodable::attrs::serde::Deserializer<'de>,
+            {
+                struct __Visitor #de_impl_generics (
+                    ::core::marker::PhantomData<(&'de (), #(#marker_params),*)>,
+                ) #de_where_clause;
+
+                impl #de_impl_generics ::encodable::attrs::serde::de::Visitor<'de>
+                    for __Visitor #visitor_generics
+                #de_where_clause
+                {
+                    type Value = #ident #ty_generics;
+
+                    fn expecting(
+                        &self,
+                        formatter: &mut ::core::fmt::Formatter,
+                    ) -> ::core::fmt::Result {
+                        formatter.write_str(#expecting)
+                    }
+
+                    #visit
+                }
+
+                #call
+            }
+        }
+    }
+}
+
+/// The `visit_map` method of a named struct's visitor, which reads the struct from formats that
+/// store it as a map, such as JSON. Unknown keys are ignored, and every field that isn't skipped
+/// must be present.
+fn expand_visit_map(
+    fields: &[Field],
+    bindings: &[Ident],
+    constructed: &TokenStream,
+) -> TokenStream {
+    let encoded: Vec<(&Field, &Ident)> = fields
+        .iter()
+        .zip(bindings)
+        .filter(|(field, _)| !field.attrs.skip)
+        .collect();
+    let slots = encoded.iter().map(|(field, binding)| {
+        let wire = field.wire_type();
+        quote!(let mut #binding: ::core::option::Option<#wire> = ::core::option::Option::None;)
+    });
+    let arms = encoded.iter().map(|(field, binding)| {
+        let name = &field.name;
+        quote! {
+            #name => {
+                if #binding.is_some() {
+                    return ::core::result::Result::Err(
+                        ::encodable::attrs::serde::de::Error::duplicate_field(#name),
+                    );
+                }
+                #binding = ::core::option::Option::Some(
+                    ::encodable::attrs::serde::de::MapAccess::next_value(&mut map)?,
+                );
+            }
+        }
+    });
+    let reads = encoded.iter().map(|(field, binding)| {
+        let name = &field.name;
+        let value = field.unwrapped(quote!(value));
+        quote! {
+            let #binding = match #binding {
+                ::core::option::Option::Some(value) => #value,
+                ::core::option::Option::None => {
+                    return ::core::result::Result::Err(
+                        ::encodable::attrs::serde::de::Error::missing_field(#name),
+                    )
+                }
+            };
+        }
+    });
+    let defaults = fields
+        .iter()
+        .zip(bindings)
+        .filter(|(field, _)| field.attrs.skip)
+        .map(|(_, binding)| quote!(let #binding = ::core::default::Default::default();));
+
+    quote! {
+        fn visit_map<__A>(self, mut map: __A) -> ::core::result::Result<Self::Value, __A::Error>
+        where
+            __A: ::encodable::attrs::serde::de::MapAccess<'de>,
+        {
+            #(#slots)*
+            while let ::core::option::Option::Some(key) =
+                ::encodable::attrs::serde::de::MapAccess::next_key::<::std::string::String>(
+                    &mut map,
+                )?
+            {
+                match key.as_str() {
+                    #(#arms)*
+                    _ => {
+                        ::encodable::attrs::serde::de::MapAccess::next_value::<
+                            ::encodable::attrs::serde::de::IgnoredAny,
+                        >(&mut map)?;
+                    }
+                }
+            }
+            #(#reads)*
+            #(#defaults)*
+            ::core::result::Result::Ok(#constructed)
+        }
+    }
+}
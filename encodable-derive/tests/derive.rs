@@ -0,0 +1,183 @@
+use encodable::{
+    config::Config, decode_padded, decode_padded_with_config, encode_padded,
+    encode_padded_with_config, error::Error, layout::Layout, names::FeatureNames, Encoding,
+};
+use encodable_derive::Encodable;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Color {
+    Red,
+    Green,
+    Blue,
+}
+
+#[derive(Debug, PartialEq, Encodable)]
+struct Pixel {
+    #[encodable(one_hot)]
+    color: Color,
+    #[encodable(scale = 0.5)]
+    brightness: u8,
+    #[encodable(lane = "f")]
+    pos: (i64, bool),
+    #[encodable(skip)]
+    label: String,
+    count: i64,
+}
+
+fn pixel() -> Pixel {
+    Pixel {
+        color: Color::Green,
+        brightness: 3,
+        pos: (2, true),
+        label: "origin".to_string(),
+        count: 4,
+    }
+}
+
+#[test]
+fn field_attributes() {
+    let encoding = encode_padded(&pixel()).unwrap();
+    assert_eq!(
+        encoding,
+        Encoding {
+            f: vec![1.5, 2.0, 1.0],
+            i: vec![4],
+            b: vec![false, true, false],
+        }
+    );
+
+    let decoded: Pixel = decode_padded(&encoding).unwrap();
+    assert_eq!(
+        decoded,
+        Pixel {
+            label: String::new(),
+            ..pixel()
+        }
+    );
+}
+
+#[test]
+fn scale_out_of_range() {
+    let mut encoding = encode_padded(&pixel()).unwrap();
+    // 200 / 0.5 doesn't fit in the u8 brightness
+    encoding.f[0] = 200.0;
+    assert!(matches!(
+        decode_padded::<Pixel>(&encoding),
+        Err(Error::DeMessage(msg)) if msg.contains("u8")
+    ));
+}
+
+#[test]
+fn maps() {
+    let json = serde_json::to_string(&pixel()).unwrap();
+    let expected = Pixel {
+        label: String::new(),
+        ..pixel()
+    };
+    assert_eq!(serde_json::from_str::<Pixel>(&json).unwrap(), expected);
+
+    // the fields can be in any order, and unknown ones are ignored
+    let json =
+        r#"{"count": 4, "label": "x", "pos": [2, true], "brightness": 1.5, "color": "Green"}"#;
+    assert_eq!(serde_json::from_str::<Pixel>(json).unwrap(), expected);
+
+    assert!(serde_json::from_str::<Pixel>(r#"{"count": 4}"#).is_err());
+    assert!(serde_json::from_str::<Pixel>(r#"{"count": 4, "count": 5}"#).is_err());
+}
+
+#[test]
+fn layout() {
+    let layout = Layout::of::<Pixel>().unwrap();
+    let encoding = encode_padded(&pixel()).unwrap();
+    assert_eq!(
+        (layout.f, layout.i, layout.b),
+        (encoding.f.len(), encoding.i.len(), encoding.b.len())
+    );
+
+    let names = FeatureNames::of::<Pixel>().unwrap();
+    assert_eq!(names.f, vec!["brightness", "pos.0", "pos.1"]);
+    assert_eq!(names.i, vec!["count"]);
+    assert_eq!(
+        names.b,
+        vec![
+            "color.Red#variant",
+            "color.Green#variant",
+            "color.Blue#variant"
+        ]
+    );
+    assert_eq!(FeatureNames::of_value(&pixel()).unwrap(), names);
+}
+
+#[test]
+fn nested_overrides() {
+    #[derive(Debug, PartialEq, Encodable)]
+    struct Foo {
+        #[encodable(one_hot, lane = "f")]
+        colors: Vec<Color>,
+        #[encodable(lane = "i")]
+        flags: Option<bool>,
+    }
+
+    let config = Config {
+        max_seq_len: Some(2),
+        ..Default::default()
+    };
+    let foo = Foo {
+        colors: vec![Color::Blue],
+        flags: None,
+    };
+    let encoding = encode_padded_with_config(&foo, config).unwrap();
+    assert_eq!(
+        encoding,
+        Encoding {
+            f: vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0],
+            i: vec![0, 0],
+            b: vec![],
+        }
+    );
+    assert_eq!(
+        decode_padded_with_config::<Foo>(&encoding, config).unwrap(),
+        foo
+    );
+
+    let names = FeatureNames::with_config::<Foo>(config).unwrap();
+    assert_eq!(names.f.len(), encoding.f.len());
+    assert_eq!(names.i, vec!["flags#some", "flags"]);
+}
+
+#[test]
+fn tuple_structs() {
+    #[derive(Debug, PartialEq, Encodable)]
+    struct Flag(#[encodable(lane = "f")] bool);
+
+    #[derive(Debug, PartialEq, Encodable)]
+    struct Pair(i64, #[encodable(skip)] u8, #[encodable(scale = 10)] f32);
+
+    let encoding = encode_padded(&(Flag(true), Pair(1, 2, 0.5))).unwrap();
+    assert_eq!(encoding.f, vec![1.0, 5.0]);
+    assert_eq!(encoding.i, vec![1]);
+
+    let names = FeatureNames::of::<(Flag, Pair)>().unwrap();
+    assert_eq!(names.f, vec!["0", "1.1"]);
+
+    let decoded: (Flag, Pair) = decode_padded(&encoding).unwrap();
+    assert_eq!(decoded, (Flag(true), Pair(1, 0, 0.5)));
+}
+
+#[test]
+fn generics() {
+    #[derive(Debug, PartialEq, Encodable)]
+    struct Wrapper<T> {
+        #[encodable(lane = "b")]
+        inner: T,
+    }
+
+    let wrapper = Wrapper { inner: (0i64, 2.0) };
+    let encoding = encode_padded(&wrapper).unwrap();
+    assert_eq!(encoding.b, vec![false, true]);
+    assert_eq!(
+        decode_padded::<Wrapper<(i64, f64)>>(&encoding).unwrap(),
+        Wrapper { inner: (0, 1.0) }
+    );
+}
@@ -0,0 +1,69 @@
+use encodable::{
+    encoded_shape::{assert_encoded_shape, EncodedShape},
+    Encodable,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, EncodedShape)]
+enum Action {
+    Move { dx: f64, dy: f64 },
+    Jump(i64),
+    Wait,
+}
+
+#[derive(Debug, Encodable, EncodedShape)]
+struct Observation {
+    position: [f32; 3],
+    velocity: Option<(f64, f64)>,
+    #[encodable(one_hot)]
+    action: Action,
+    #[encodable(scale = 0.1)]
+    health: u8,
+    #[encodable(lane = "f")]
+    grounded: bool,
+    #[encodable(skip)]
+    #[allow(dead_code)]
+    label: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, EncodedShape)]
+struct Pair<T>(T, T);
+
+fn observation() -> Observation {
+    Observation {
+        position: [1.0, 2.0, 3.0],
+        velocity: None,
+        action: Action::Jump(2),
+        health: 7,
+        grounded: true,
+        label: "player".to_string(),
+    }
+}
+
+#[test]
+fn lane_lengths() {
+    assert_eq!(Observation::F_LEN, 3 + 2 + 2 + 1 + 1);
+    assert_eq!(Observation::I_LEN, 1);
+    assert_eq!(Observation::B_LEN, 1 + 3);
+
+    // the constants can size arrays
+    let buffer = [0.0f32; Observation::F_LEN];
+    assert_eq!(buffer.len(), 9);
+
+    assert_eq!(<Action as EncodedShape>::F_LEN, 2);
+    assert_eq!(<Action as EncodedShape>::I_LEN, 2);
+    assert_eq!(<Action as EncodedShape>::ONE_HOT_LENS, [2, 1, 3]);
+    assert_eq!(<Pair<Option<u8>> as EncodedShape>::B_LEN, 2);
+}
+
+#[test]
+fn agrees_with_encode() {
+    assert_encoded_shape(&observation());
+    assert_encoded_shape(&Observation {
+        velocity: Some((1.0, 2.0)),
+        action: Action::Wait,
+        ..observation()
+    });
+    assert_encoded_shape(&[Action::Move { dx: 1.0, dy: 2.0 }, Action::Jump(3)]);
+    assert_encoded_shape(&Pair(Some(Action::Wait), None));
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::disallowed_names)]
+
 use encodable::{decode, encode};
 use serde::{Deserialize, Serialize};
 
@@ -0,0 +1,195 @@
+//! The wrappers used by `#[derive(Encodable)]` to apply its field attributes.
+//!
+//! Wrapped values are serialized as newtype structs with reserved names, which the
+//! [`Serializer`](crate::serializer::Serializer) and the
+//! [`Deserializer`](crate::deserializer::Deserializer) recognize. Other serde formats see
+//! them as plain newtypes.
+
+use std::marker::PhantomData;
+
+use serde::{
+    de::{Unexpected, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use super::{config::VariantRepr, Lane};
+
+#[doc(hidden)]
+pub use serde;
+
+const ONE_HOT: &str = "$encodable::one_hot";
+const LANE_F: &str = "$encodable::lane::f";
+const LANE_I: &str = "$encodable::lane::i";
+const LANE_B: &str = "$encodable::lane::b";
+
+/// How a wrapper changes the way the value inside of it is encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Override {
+    /// Enums are stored with this variant representation.
+    Variants(VariantRepr),
+    /// Every value is stored in this lane.
+    Lane(Lane),
+}
+
+impl Override {
+    /// The override of a newtype struct with a reserved name.
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            ONE_HOT => Some(Self::Variants(VariantRepr::OneHot)),
+            LANE_F => Some(Self::Lane(Lane::F)),
+            LANE_I => Some(Self::Lane(Lane::I)),
+            LANE_B => Some(Self::Lane(Lane::B)),
+            _ => None,
+        }
+    }
+}
+
+/// Stores the enums inside of `T` as a one-hot block in the bools vector, whatever the config's
+/// [`VariantRepr`] is. Applied by `#[encodable(one_hot)]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OneHot<T>(pub T);
+
+/// Stores every value inside of `T` in the lane `LANE`, one of `'f'`, `'i'` or `'b'`. Applied by
+/// `#[encodable(lane = "f")]`.
+///
+/// Values are converted to the lane's type: bools become `0` or `1`, and any non-zero value
+/// becomes `true`. Floats stored in the ints lane are truncated. Values read back from the floats
+/// lane are rounded to the nearest int, and floats of at least `0.5` are read as `true`, so that
+/// model outputs can be decoded directly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InLane<T, const LANE: char>(pub T);
+
+/// Stores a number multiplied by a factor, as a float. Applied by `#[encodable(scale = 0.5)]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scaled<'a, T>(pub &'a T, pub f64);
+
+/// Numbers that can be scaled by [`Scaled`].
+pub trait Scale: Sized {
+    fn to_f64(&self) -> f64;
+
+    /// Converts back from a float, rounding to the nearest value for integers, or `None` if the
+    /// float is out of the type's range.
+    fn from_f64(f: f64) -> Option<Self>;
+}
+
+impl Scale for f32 {
+    fn to_f64(&self) -> f64 {
+        (*self).into()
+    }
+
+    fn from_f64(f: f64) -> Option<Self> {
+        let narrowed = f as f32;
+        // infinities and NaNs are kept, finite values must stay finite
+        (narrowed.is_finite() || !f.is_finite()).then_some(narrowed)
+    }
+}
+
+impl Scale for f64 {
+    fn to_f64(&self) -> f64 {
+        *self
+    }
+
+    fn from_f64(f: f64) -> Option<Self> {
+        Some(f)
+    }
+}
+
+macro_rules! impl_scale_ints {
+    ($($ty:ty),*) => {
+        $(
+            impl Scale for $ty {
+                fn to_f64(&self) -> f64 {
+                    *self as f64
+                }
+
+                fn from_f64(f: f64) -> Option<Self> {
+                    // every finite float out of the range of i128 is out of the range of the type
+                    // as well, so saturating to it is fine
+                    f.is_finite()
+                        .then(|| f.round() as i128)
+                        .and_then(|i| i.try_into().ok())
+                }
+            }
+        )*
+    };
+}
+
+impl_scale_ints!(i8, i16, i32, i64, u8, u16, u32, u64);
+
+/// Divides `f` by `scale` and converts it back into `T`, failing if it's out of `T`'s range.
+/// Used by `#[derive(Encodable)]` to read a field with `#[encodable(scale = k)]`.
+#[doc(hidden)]
+pub fn unscale<T, E>(f: f64, scale: f64) -> Result<T, E>
+where
+    T: Scale,
+    E: serde::de::Error,
+{
+    let f = f / scale;
+    T::from_f64(f).ok_or_else(|| {
+        let expected = format!("a number in the range of {}", std::any::type_name::<T>());
+        E::invalid_value(Unexpected::Float(f), &expected.as_str())
+    })
+}
+
+impl<T: Serialize> Serialize for OneHot<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(ONE_HOT, &self.0)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for OneHot<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer
+            .deserialize_newtype_struct(ONE_HOT, Newtype(PhantomData))
+            .map(Self)
+    }
+}
+
+/// The reserved name of a lane.
+fn lane_name<E: serde::de::Error>(lane: char) -> Result<&'static str, E> {
+    match lane {
+        'f' => Ok(LANE_F),
+        'i' => Ok(LANE_I),
+        'b' => Ok(LANE_B),
+        _ => Err(E::custom(format!(
+            "`{lane}` is not a lane, expected one of f, i or b"
+        ))),
+    }
+}
+
+impl<T: Serialize, const LANE: char> Serialize for InLane<T, LANE> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let name = lane_name::<serde::de::value::Error>(LANE)
+            .map_err(<S::Error as serde::ser::Error>::custom)?;
+        serializer.serialize_newtype_struct(name, &self.0)
+    }
+}
+
+impl<'de, T: Deserialize<'de>, const LANE: char> Deserialize<'de> for InLane<T, LANE> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer
+            .deserialize_newtype_struct(lane_name(LANE)?, Newtype(PhantomData))
+            .map(Self)
+    }
+}
+
+impl<T: Scale> Serialize for Scaled<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.0.to_f64() * self.1)
+    }
+}
+
+/// Visits a newtype struct, deserializing the value inside of it as a `T`.
+struct Newtype<T>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for Newtype<T> {
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a newtype struct")
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, deserializer: D) -> Result<T, D::Error> {
+        T::deserialize(deserializer)
+    }
+}
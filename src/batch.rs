@@ -0,0 +1,593 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    config::Config,
+    deserializer::Deserializer,
+    error::{Error, Result},
+    serializer::Serializer,
+    shape::{Shape, Width},
+    Lane,
+};
+
+/// The encodings of many values, stored as three row-major matrices with one row per value.
+///
+/// Row `r` of the floats matrix is `f[r * f_width..(r + 1) * f_width]`, and likewise for the ints
+/// and bools matrices.
+///
+/// Example:
+/// ```rust
+/// use encodable::batch::encode_batch;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Deserialize, Serialize)]
+/// struct Foo {
+///     a: f64,
+///     b: (i64, i64),
+/// }
+///
+/// let foos = [Foo { a: 1.0, b: (2, 3) }, Foo { a: 4.0, b: (5, 6) }];
+/// let batch = encode_batch(&foos).unwrap();
+/// assert_eq!((batch.rows, batch.f_width, batch.i_width), (2, 1, 2));
+/// assert_eq!(batch.f, vec![1.0, 4.0]);
+/// assert_eq!(batch.i, vec![2, 3, 5, 6]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BatchEncoding {
+    /// The number of encoded values.
+    pub rows: usize,
+    /// The number of floats in each row.
+    pub f_width: usize,
+    /// The number of ints in each row.
+    pub i_width: usize,
+    /// The number of bools in each row.
+    pub b_width: usize,
+    pub f: Vec<f64>,
+    pub i: Vec<i64>,
+    pub b: Vec<bool>,
+}
+
+impl BatchEncoding {
+    /// The number of values in each row of `lane`.
+    pub fn width(&self, lane: Lane) -> usize {
+        match lane {
+            Lane::F => self.f_width,
+            Lane::I => self.i_width,
+            Lane::B => self.b_width,
+        }
+    }
+
+    /// The floats, ints and bools of a row, borrowed from the matrices.
+    pub fn row(&self, row: usize) -> Option<(&[f64], &[i64], &[bool])> {
+        if row >= self.rows {
+            return None;
+        }
+        Some((
+            self.f.get(row * self.f_width..(row + 1) * self.f_width)?,
+            self.i.get(row * self.i_width..(row + 1) * self.i_width)?,
+            self.b.get(row * self.b_width..(row + 1) * self.b_width)?,
+        ))
+    }
+}
+
+/// Encodes every value of `values` into one row of a [`BatchEncoding`].
+///
+/// Every value must be encoded with the same number of values in each lane, so sequences need
+/// [`Config::max_seq_len`] unless they all have the same length.
+pub fn encode_batch<'de, T>(values: &[T]) -> Result<BatchEncoding>
+where
+    T: Serialize + Deserialize<'de>,
+{
+    encode_batch_with_config(values, Config::default())
+}
+
+/// Encodes every value of `values` into one row of a [`BatchEncoding`] using a custom config.
+pub fn encode_batch_with_config<'de, T>(values: &[T], config: Config) -> Result<BatchEncoding>
+where
+    T: Serialize + Deserialize<'de>,
+{
+    let shape = Arc::new(Shape::trace::<T>()?);
+    // types without a fixed width take the width of the first row
+    let mut width = shape.encoded_width(&config);
+    let mut serializer = Serializer::default().with_config(config).with_shape(shape);
+    if let Some(width) = width {
+        serializer.reserve(width * values.len());
+    }
+
+    for (row, value) in values.iter().enumerate() {
+        let start = serializer.written();
+        value.serialize(&mut serializer)?;
+        let row_width = serializer.written() - start;
+        check_row_width(row, *width.get_or_insert(row_width), row_width)?;
+    }
+
+    let width = width.unwrap_or_default();
+    let encoding = serializer.consume();
+    Ok(BatchEncoding {
+        rows: values.len(),
+        f_width: width.f,
+        i_width: width.i,
+        b_width: width.b,
+        f: encoding.f,
+        i: encoding.i,
+        b: encoding.b,
+    })
+}
+
+/// Fails if row `row` was encoded with a different width than the rows before it.
+fn check_row_width(row: usize, expected: Width, width: Width) -> Result<()> {
+    for (lane, expected, len) in [
+        (Lane::F, expected.f, width.f),
+        (Lane::I, expected.i, width.i),
+        (Lane::B, expected.b, width.b),
+    ] {
+        if expected != len {
+            return Err(Error::RowWidthMismatch {
+                row,
+                lane,
+                expected,
+                len,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Decodes every row of `batch`, failing at the first row that can't be decoded with
+/// [`Error::InRow`].
+///
+/// The matrices can be the output of a model: `batch` only has to hold `rows` rows of the given
+/// widths.
+pub fn decode_batch<'de, T>(batch: &'de BatchEncoding) -> Result<Vec<T>>
+where
+    T: Deserialize<'de>,
+{
+    decode_batch_with_config(batch, Config::default())
+}
+
+/// Decodes every row of `batch` that was encoded using a custom config, failing at the first row
+/// that can't be decoded.
+pub fn decode_batch_with_config<'de, T>(batch: &'de BatchEncoding, config: Config) -> Result<Vec<T>>
+where
+    T: Deserialize<'de>,
+{
+    let shape = batch_shape::<T>(batch)?;
+    (0..batch.rows)
+        .map(|row| decode_row(batch, row, config, &shape).map_err(in_row(row)))
+        .collect()
+}
+
+/// Wraps the error of row `row`.
+fn in_row(row: usize) -> impl FnOnce(Error) -> Error {
+    move |err| Error::InRow {
+        row,
+        source: Box::new(err),
+    }
+}
+
+/// Decodes every row of `batch`, returning the result of each row. Only errors that concern the
+/// whole batch, such as matrices of the wrong size, fail outright.
+pub fn decode_batch_rows<'de, T>(batch: &'de BatchEncoding) -> Result<Vec<Result<T>>>
+where
+    T: Deserialize<'de>,
+{
+    decode_batch_rows_with_config(batch, Config::default())
+}
+
+/// Decodes every row of `batch` that was encoded using a custom config, returning the result of
+/// each row.
+pub fn decode_batch_rows_with_config<'de, T>(
+    batch: &'de BatchEncoding,
+    config: Config,
+) -> Result<Vec<Result<T>>>
+where
+    T: Deserialize<'de>,
+{
+    let shape = batch_shape::<T>(batch)?;
+    Ok((0..batch.rows)
+        .map(|row| decode_row(batch, row, config, &shape))
+        .collect())
+}
+
+/// The shape of `T`, after checking that the matrices of `batch` hold every row.
+fn batch_shape<'de, T>(batch: &BatchEncoding) -> Result<Arc<Shape>>
+where
+    T: Deserialize<'de>,
+{
+    let shape = Arc::new(Shape::trace::<T>()?);
+    for (lane, len) in [
+        (Lane::F, batch.f.len()),
+        (Lane::I, batch.i.len()),
+        (Lane::B, batch.b.len()),
+    ] {
+        let expected = batch.rows * batch.width(lane);
+        if expected != len {
+            return Err(Error::LayoutMismatch {
+                lane,
+                expected,
+                len,
+            });
+        }
+    }
+
+    Ok(shape)
+}
+
+/// Decodes row `row` of `batch`, reading it in place.
+fn decode_row<'de, T>(
+    batch: &'de BatchEncoding,
+    row: usize,
+    config: Config,
+    shape: &Arc<Shape>,
+) -> Result<T>
+where
+    T: Deserialize<'de>,
+{
+    let (f, i, b) = batch.row(row).ok_or(Error::IndexOutOfBounds {
+        lane: Lane::F,
+        index: row,
+        len: batch.rows,
+        path: String::new(),
+    })?;
+    let mut deserializer = Deserializer::from_slices(f, i, b)
+        .with_config(config)
+        .with_shape(shape.clone());
+    let value = T::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+/// Encodes every value of `values` into one row of a [`BatchEncoding`], using every thread of
+/// rayon's pool. The encoding is the same as the one of [`encode_batch`].
+#[cfg(feature = "rayon")]
+pub fn par_encode_batch<'de, T>(values: &[T]) -> Result<BatchEncoding>
+where
+    T: Serialize + Deserialize<'de> + Sync,
+{
+    par_encode_batch_with_config(values, Config::default())
+}
+
+/// Encodes every value of `values` into one row of a [`BatchEncoding`] using a custom config,
+/// using every thread of rayon's pool.
+#[cfg(feature = "rayon")]
+pub fn par_encode_batch_with_config<'de, T>(values: &[T], config: Config) -> Result<BatchEncoding>
+where
+    T: Serialize + Deserialize<'de> + Sync,
+{
+    use rayon::prelude::*;
+
+    use crate::serializer::Slices;
+
+    let shape = Arc::new(Shape::trace::<T>()?);
+    let width = match (shape.encoded_width(&config), values.first()) {
+        (Some(width), _) => width,
+        // types without a fixed width take the width of the first row
+        (None, Some(first)) => {
+            let mut serializer = Serializer::default()
+                .with_config(config)
+                .with_shape(shape.clone());
+            first.serialize(&mut serializer)?;
+            serializer.written()
+        }
+        (None, None) => Width::default(),
+    };
+
+    let rows = values.len();
+    let mut f = vec![0.0; rows * width.f];
+    let mut i = vec![0; rows * width.i];
+    let mut b = vec![false; rows * width.b];
+    let error = rows_mut(&mut f, width.f, rows)
+        .into_par_iter()
+        .zip(rows_mut(&mut i, width.i, rows))
+        .zip(rows_mut(&mut b, width.b, rows))
+        .zip(values)
+        .enumerate()
+        .map_init(
+            || {
+                Serializer::default()
+                    .with_config(config)
+                    .with_shape(shape.clone())
+                    .with_output(Slices::default())
+            },
+            |serializer, (row, (((f, i), b), value))| {
+                serializer.replace_output(Slices::new(f, i, b));
+                match value.serialize(&mut *serializer) {
+                    Ok(()) => check_row_width(row, width, serializer.written()),
+                    // the row is wider than the first one, measure it to report by how much
+                    Err(Error::SliceTooShort { .. }) => {
+                        let mut measured = Serializer::default()
+                            .with_config(config)
+                            .with_shape(shape.clone());
+                        value
+                            .serialize(&mut measured)
+                            .and_then(|()| check_row_width(row, width, measured.written()))
+                    }
+                    Err(err) => Err(err),
+                }
+                .err()
+                .map(|err| (row, err))
+            },
+        )
+        .flatten()
+        .min_by_key(|(row, _)| *row);
+    if let Some((_, err)) = error {
+        return Err(err);
+    }
+
+    Ok(BatchEncoding {
+        rows,
+        f_width: width.f,
+        i_width: width.i,
+        b_width: width.b,
+        f,
+        i,
+        b,
+    })
+}
+
+/// Splits a row-major matrix into its rows, which may be empty.
+#[cfg(feature = "rayon")]
+fn rows_mut<T>(matrix: &mut [T], width: usize, rows: usize) -> Vec<&mut [T]> {
+    if width == 0 {
+        return (0..rows).map(|_| <&mut [T]>::default()).collect();
+    }
+    matrix.chunks_mut(width).collect()
+}
+
+/// Decodes every row of `batch` using every thread of rayon's pool, failing at a row that can't
+/// be decoded.
+#[cfg(feature = "rayon")]
+pub fn par_decode_batch<'de, T>(batch: &'de BatchEncoding) -> Result<Vec<T>>
+where
+    T: Deserialize<'de> + Send,
+{
+    par_decode_batch_with_config(batch, Config::default())
+}
+
+/// Decodes every row of `batch` that was encoded using a custom config, using every thread of
+/// rayon's pool. The other threads stop once a row fails; if several rows fail, which of them
+/// is reported depends on the order the threads reached them in.
+#[cfg(feature = "rayon")]
+pub fn par_decode_batch_with_config<'de, T>(
+    batch: &'de BatchEncoding,
+    config: Config,
+) -> Result<Vec<T>>
+where
+    T: Deserialize<'de> + Send,
+{
+    use rayon::prelude::*;
+
+    let shape = batch_shape::<T>(batch)?;
+    (0..batch.rows)
+        .into_par_iter()
+        .map(|row| decode_row(batch, row, config, &shape).map_err(in_row(row)))
+        .collect()
+}
+
+/// Decodes every row of `batch` using every thread of rayon's pool, returning the result of each
+/// row.
+#[cfg(feature = "rayon")]
+pub fn par_decode_batch_rows<'de, T>(batch: &'de BatchEncoding) -> Result<Vec<Result<T>>>
+where
+    T: Deserialize<'de> + Send,
+{
+    par_decode_batch_rows_with_config(batch, Config::default())
+}
+
+/// Decodes every row of `batch` that was encoded using a custom config, using every thread of
+/// rayon's pool, returning the result of each row.
+#[cfg(feature = "rayon")]
+pub fn par_decode_batch_rows_with_config<'de, T>(
+    batch: &'de BatchEncoding,
+    config: Config,
+) -> Result<Vec<Result<T>>>
+where
+    T: Deserialize<'de> + Send,
+{
+    use rayon::prelude::*;
+
+    let shape = batch_shape::<T>(batch)?;
+    Ok((0..batch.rows)
+        .into_par_iter()
+        .map(|row| decode_row(batch, row, config, &shape))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::{
+        decode_batch, decode_batch_rows, decode_batch_with_config, encode_batch,
+        encode_batch_with_config, BatchEncoding,
+    };
+    use crate::{config::Config, encode_padded_with_config, error::Error, Lane};
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    enum Shape {
+        Circle(f64),
+        Rect { w: f64, h: f64 },
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Foo {
+        a: Option<Shape>,
+        b: Vec<i64>,
+        c: bool,
+    }
+
+    #[test]
+    fn rows() {
+        let config = Config {
+            max_seq_len: Some(2),
+            ..Default::default()
+        };
+        let foos = [
+            Foo {
+                a: Some(Shape::Rect { w: 1.0, h: 2.0 }),
+                b: vec![3],
+                c: true,
+            },
+            Foo {
+                a: None,
+                b: vec![4, 5, 6],
+                c: false,
+            },
+            Foo {
+                a: Some(Shape::Circle(7.0)),
+                b: vec![],
+                c: true,
+            },
+        ];
+        let batch = encode_batch_with_config(&foos, config).unwrap();
+        assert_eq!(batch.rows, 3);
+        assert_eq!((batch.f_width, batch.i_width, batch.b_width), (2, 4, 2));
+
+        // the rows are the encodings of the values, one after the other
+        let mut expected = (Vec::new(), Vec::new(), Vec::new());
+        for foo in &foos {
+            let encoding = encode_padded_with_config(foo, config).unwrap();
+            expected.0.extend(encoding.f);
+            expected.1.extend(encoding.i);
+            expected.2.extend(encoding.b);
+        }
+        assert_eq!(
+            (&batch.f, &batch.i, &batch.b),
+            (&expected.0, &expected.1, &expected.2)
+        );
+
+        let decoded: Vec<Foo> = decode_batch_with_config(&batch, config).unwrap();
+        assert_eq!(decoded[0], foos[0]);
+        assert_eq!(decoded[1].b, vec![4, 5]);
+        assert_eq!(decoded[2], foos[2]);
+
+        let batch = encode_batch::<(f64, bool)>(&[]).unwrap();
+        assert_eq!(batch.rows, 0);
+        assert_eq!(batch.width(Lane::B), 1);
+        assert!(batch.f.is_empty());
+    }
+
+    #[test]
+    fn row_width_mismatch() {
+        let rows = [vec![1.0], vec![2.0], vec![3.0, 4.0]];
+        let err = encode_batch(&rows).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::RowWidthMismatch {
+                row: 2,
+                lane: Lane::F,
+                expected: 1,
+                len: 2,
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Row 2 holds 2 values in the f lane, but the rows before it hold 1"
+        );
+
+        // sequences of the same length have the same width
+        let batch = encode_batch(&[vec![1.0, 2.0], vec![3.0, 4.0]]).unwrap();
+        assert_eq!((batch.f_width, batch.i_width), (2, 1));
+    }
+
+    #[test]
+    fn decode_rows() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        enum Color {
+            Red,
+            Green,
+        }
+
+        // the outputs of a model, one row per value
+        let batch = BatchEncoding {
+            rows: 3,
+            f_width: 1,
+            i_width: 1,
+            b_width: 0,
+            f: vec![0.5, 1.5, 2.5],
+            i: vec![1, 2, 0],
+            b: vec![],
+        };
+
+        let rows = decode_batch_rows::<(f64, Color)>(&batch).unwrap();
+        assert_eq!(rows[0].as_ref().unwrap(), &(0.5, Color::Green));
+        assert!(matches!(
+            rows[1],
+            Err(Error::VariantOutOfRange { index: 2, .. })
+        ));
+        assert_eq!(rows[2].as_ref().unwrap(), &(2.5, Color::Red));
+
+        let err = decode_batch::<(f64, Color)>(&batch).unwrap_err();
+        assert!(matches!(&err, Error::InRow { row: 1, .. }));
+        assert_eq!(
+            err.to_string(),
+            "Row 1: Variant 2 is out of range for an enum with 2 variants at `1`"
+        );
+
+        // rows that are too wide aren't read to the end
+        assert!(matches!(
+            decode_batch::<(f64,)>(&batch),
+            Err(Error::InRow { row: 0, .. })
+        ));
+
+        let batch = BatchEncoding { rows: 4, ..batch };
+        assert!(matches!(
+            decode_batch_rows::<(f64, Color)>(&batch),
+            Err(Error::LayoutMismatch {
+                lane: Lane::F,
+                expected: 4,
+                len: 3,
+            })
+        ));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel() {
+        use super::{par_decode_batch, par_encode_batch, par_encode_batch_with_config};
+
+        let config = Config {
+            max_seq_len: Some(3),
+            ..Default::default()
+        };
+        let foos: Vec<Foo> = (0..1000)
+            .map(|n| Foo {
+                a: match n % 3 {
+                    0 => None,
+                    1 => Some(Shape::Circle(n as f64)),
+                    _ => Some(Shape::Rect {
+                        w: n as f64,
+                        h: -(n as f64),
+                    }),
+                },
+                b: (0..n % 5).collect(),
+                c: n % 2 == 0,
+            })
+            .collect();
+        let batch = par_encode_batch_with_config(&foos, config).unwrap();
+        assert_eq!(batch, encode_batch_with_config(&foos, config).unwrap());
+
+        // without a fixed width, the first row sets it and the first row that differs fails
+        let rows: Vec<Vec<f64>> = (0..1000)
+            .map(|n| vec![n as f64; 1 + usize::from(n == 700 || n == 900)])
+            .collect();
+        assert!(matches!(
+            par_encode_batch(&rows),
+            Err(Error::RowWidthMismatch { row: 700, .. })
+        ));
+
+        let pairs: Vec<(f64, bool)> = (0..1000).map(|n| (n as f64, n % 3 == 0)).collect();
+        let batch = par_encode_batch(&pairs).unwrap();
+        assert_eq!(batch, encode_batch(&pairs).unwrap());
+        assert_eq!(par_decode_batch::<(f64, bool)>(&batch).unwrap(), pairs);
+
+        // decoding stops at a row that fails
+        let ints: Vec<i64> = (0..1000).collect();
+        let batch = par_encode_batch(&ints).unwrap();
+        assert!(matches!(
+            par_decode_batch::<u8>(&batch),
+            Err(Error::InRow { row, .. }) if row >= 256
+        ));
+    }
+}
@@ -0,0 +1,70 @@
+use super::Lane;
+
+/// Settings shared by the [`Serializer`](crate::serializer::Serializer) and the
+/// [`Deserializer`](crate::deserializer::Deserializer).
+///
+/// An encoding must be decoded with the same config it was encoded with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Config {
+    pub wide_ints: WideIntPolicy,
+    pub f32s: F32Policy,
+    /// When set, sequences are truncated or padded to this many elements, so that they always
+    /// occupy the same number of values. Their length prefix still holds the number of elements
+    /// that were kept.
+    pub max_seq_len: Option<usize>,
+    pub variants: VariantRepr,
+    /// When set, every value is stored in the float lane, in field order, as if the whole value
+    /// were wrapped in [`InLane`](crate::attrs::InLane) with [`Lane::F`]. Ints are cast to floats
+    /// and bools become `0.0` or `1.0`, so the encoding is a single feature vector. Decoding rounds
+    /// floats to the nearest int and reads floats of at least `0.5` as `true`.
+    pub all_floats: bool,
+}
+
+impl Config {
+    /// The lane every value is stored in, if it isn't the value's own.
+    pub(crate) fn single_lane(&self) -> Option<Lane> {
+        self.all_floats.then_some(Lane::F)
+    }
+}
+
+/// How `u64`, `i128` and `u128` values are stored in the `i64` int lane.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WideIntPolicy {
+    /// One slot per value. Values outside of the `i64` range are rejected.
+    #[default]
+    Reject,
+    /// One slot per value, holding the value's low 64 bits. `u64` values are always stored
+    /// losslessly, 128-bit values are rejected if they don't fit in 64 bits.
+    Reinterpret,
+    /// Two slots per value, holding the high and then the low 64 bits of the value.
+    Split,
+}
+
+/// How `f64` values from the float lane are narrowed back into `f32` fields when decoding.
+///
+/// Finite values that are too large for an `f32` never silently become infinite: they are either
+/// clamped or rejected with [`Error::FloatOverflow`](crate::error::Error::FloatOverflow).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum F32Policy {
+    /// Narrow using `as`, rounding to the nearest `f32`.
+    #[default]
+    Cast,
+    /// Like [`F32Policy::Cast`], but values out of range are clamped to `f32::MIN`/`f32::MAX`.
+    Saturate,
+    /// Reject any value that can't be represented exactly as an `f32`.
+    Exact,
+}
+
+/// How the variant of an enum is stored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum VariantRepr {
+    /// The index of the variant, in the int lane.
+    #[default]
+    Index,
+    /// One bool per variant, in the bool lane, with only the variant's bool set.
+    OneHot,
+    /// One float per variant, in the float lane, with the variant's float set to `1.0` and the
+    /// rest set to `0.0`. When decoding, the variant with the largest float is chosen, so model
+    /// outputs such as probabilities can be decoded directly.
+    OneHotFloat,
+}
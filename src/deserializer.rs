@@ -1,68 +1,315 @@
-use serde::de::{DeserializeSeed, SeqAccess};
+use std::sync::Arc;
+
+use serde::{
+    de::{DeserializeSeed, EnumAccess, IntoDeserializer, SeqAccess, VariantAccess},
+    Deserialize,
+};
 
 use super::{
+    attrs::Override,
+    config::{Config, F32Policy, VariantRepr, WideIntPolicy},
     error::{Error, Result},
-    Encoding,
+    shape::{display_path, element_segment, Segment, Shape, Width},
+    Encoding, Lane,
 };
 
 pub struct Deserializer<'de> {
-    encoding: &'de Encoding,
+    f: &'de [f64],
+    i: &'de [i64],
+    b: &'de [bool],
+    config: Config,
+    /// The shape of the value being deserialized. Without it, absent values are assumed to
+    /// have no padding.
+    shape: Option<Arc<Shape>>,
+    /// The path from the root to the value currently being deserialized.
+    path: Vec<Segment>,
+    /// The lane every value is read from, if it was overridden.
+    lane: Option<Lane>,
     f_i: usize,
     i_i: usize,
     b_i: usize,
 }
 
 impl<'de> Deserializer<'de> {
+    /// Reads from the lanes of `encoding`. Unless the type being read has no options, enums or
+    /// padded sequences, the deserializer needs its shape, given by [`Self::with_shape_of`], to
+    /// skip the padding of absent values.
     pub fn from_encoding(encoding: &'de Encoding) -> Self {
+        Self::from_slices(&encoding.f, &encoding.i, &encoding.b)
+    }
+
+    /// Reads from borrowed lanes, such as the rows of a
+    /// [`BatchEncoding`](crate::batch::BatchEncoding), a model's output buffers or a
+    /// memory-mapped file, without copying them into an [`Encoding`].
+    ///
+    /// As with [`Self::from_encoding`], types with options, enums or padded sequences can only be
+    /// read once [`Self::with_shape_of`] has been called. [`decode_slices`](crate::decode_slices)
+    /// does that for you and also checks that every value was read.
+    pub fn from_slices(f: &'de [f64], i: &'de [i64], b: &'de [bool]) -> Self {
         Self {
-            encoding,
+            f,
+            i,
+            b,
+            config: Config::default(),
+            shape: None,
+            path: Vec::new(),
+            lane: None,
             f_i: 0,
             i_i: 0,
             b_i: 0,
         }
     }
 
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = config;
+        self.lane = config.single_lane();
+        self
+    }
+
+    pub(crate) fn with_shape(mut self, shape: Arc<Shape>) -> Self {
+        self.shape = Some(shape);
+        self
+    }
+
+    /// Traces the shape of `T`, which tells the deserializer how much padding follows an absent
+    /// option, a variant or a sequence shorter than [`Config::max_seq_len`].
+    pub fn with_shape_of<'a, T>(self) -> Result<Self>
+    where
+        T: Deserialize<'a>,
+    {
+        Ok(self.with_shape(Arc::new(Shape::trace::<T>()?)))
+    }
+
     pub fn completed(&self) -> bool {
-        self.f_i == self.encoding.f.len()
-            && self.i_i == self.encoding.i.len()
-            && self.b_i == self.encoding.b.len()
+        self.f_i == self.f.len() && self.i_i == self.i.len() && self.b_i == self.b.len()
     }
 
+    /// Fails if any of the encoding's vectors haven't been read to the end.
+    pub fn end(&self) -> Result<()> {
+        [Lane::F, Lane::I, Lane::B]
+            .into_iter()
+            .map(|lane| (lane, self.position(lane), self.lane_len(lane)))
+            .find(|(_, index, len)| index != len)
+            .map_or(Ok(()), |(lane, index, len)| {
+                Err(Error::Incomplete { lane, index, len })
+            })
+    }
+
+    /// The index of the next value to be read from `lane`.
+    fn position(&self, lane: Lane) -> usize {
+        match lane {
+            Lane::F => self.f_i,
+            Lane::I => self.i_i,
+            Lane::B => self.b_i,
+        }
+    }
+
+    /// The number of values in `lane`.
+    fn lane_len(&self, lane: Lane) -> usize {
+        match lane {
+            Lane::F => self.f.len(),
+            Lane::I => self.i.len(),
+            Lane::B => self.b.len(),
+        }
+    }
+
+    /// The dotted path from the root to the value currently being deserialized.
+    fn current_path(&self) -> String {
+        display_path(&self.path)
+    }
+
+    /// An error for reading `index` past the end of `lane`.
+    fn out_of_bounds(&self, lane: Lane, index: usize) -> Error {
+        Error::IndexOutOfBounds {
+            lane,
+            index,
+            len: self.lane_len(lane),
+            path: self.current_path(),
+        }
+    }
+
+    /// The shape of the value currently being deserialized, if known.
+    fn current_shape(&self) -> Option<&Shape> {
+        self.shape.as_deref()?.at(&self.path)
+    }
+
+    /// An error for a kind of value the encoding can't represent, at the current path.
+    fn unsupported(&self, kind: &'static str) -> Error {
+        Error::Unsupported {
+            kind,
+            path: self.current_path(),
+        }
+    }
+
+    /// Skips over the padding written in place of an absent value.
+    fn skip(&mut self, width: Width) -> Result<()> {
+        let width = self.lane.map_or(width, |lane| width.in_lane(lane));
+        for (lane, width) in [(Lane::F, width.f), (Lane::I, width.i), (Lane::B, width.b)] {
+            let end = self.position(lane) + width;
+            if end > self.lane_len(lane) {
+                return Err(self.out_of_bounds(lane, end - 1));
+            }
+        }
+        self.f_i += width.f;
+        self.i_i += width.i;
+        self.b_i += width.b;
+        Ok(())
+    }
+
+    /// Reads the next float, converting it from the overridden lane if there is one.
     fn next_float(&mut self) -> Result<f64> {
+        match self.lane {
+            None | Some(Lane::F) => self.read_float(),
+            Some(Lane::I) => Ok(self.read_int()? as f64),
+            Some(Lane::B) => Ok(if self.read_bool()? { 1.0 } else { 0.0 }),
+        }
+    }
+
+    fn read_float(&mut self) -> Result<f64> {
         let f = self
-            .encoding
             .f
             .get(self.f_i)
             .copied()
-            .ok_or(Error::FloatIndexOutOfBounds)?;
+            .ok_or_else(|| self.out_of_bounds(Lane::F, self.f_i))?;
         self.f_i += 1;
         Ok(f)
     }
 
+    /// Reads the next float and narrows it to `f32` according to the f32 policy.
+    fn next_f32(&mut self) -> Result<f32> {
+        let f = self.next_float()?;
+        let narrowed = f as f32;
+        if narrowed.is_infinite() && f.is_finite() {
+            return match self.config.f32s {
+                F32Policy::Saturate => Ok(narrowed.clamp(f32::MIN, f32::MAX)),
+                F32Policy::Cast | F32Policy::Exact => Err(Error::FloatOverflow {
+                    value: f,
+                    path: self.current_path(),
+                }),
+            };
+        }
+        if self.config.f32s == F32Policy::Exact && !f.is_nan() && f64::from(narrowed) != f {
+            return Err(Error::FloatPrecisionLoss {
+                value: f,
+                path: self.current_path(),
+            });
+        }
+        Ok(narrowed)
+    }
+
+    /// Reads the next int, converting it from the overridden lane if there is one.
     fn next_int(&mut self) -> Result<i64> {
+        match self.lane {
+            None | Some(Lane::I) => self.read_int(),
+            Some(Lane::F) => Ok(self.read_float()?.round() as i64),
+            Some(Lane::B) => Ok(self.read_bool()?.into()),
+        }
+    }
+
+    fn read_int(&mut self) -> Result<i64> {
         let i = self
-            .encoding
             .i
             .get(self.i_i)
             .copied()
-            .ok_or(Error::IntIndexOutOfBounds)?;
+            .ok_or_else(|| self.out_of_bounds(Lane::I, self.i_i))?;
         self.i_i += 1;
         Ok(i)
     }
 
+    /// Reads the next int and narrows it to `T`, failing if it doesn't fit.
+    fn next_int_as<T>(&mut self) -> Result<T>
+    where
+        T: TryFrom<i64>,
+    {
+        let i = self.next_int()?;
+        T::try_from(i).map_err(|_| Error::IntOutOfRange {
+            value: i.into(),
+            ty: std::any::type_name::<T>(),
+            path: self.current_path(),
+        })
+    }
+
+    /// Reads a `u64`, `i128` or `u128` value according to the wide int policy, returning its
+    /// 128-bit two's complement representation. Values stored in a single slot are sign
+    /// extended, unless `unsigned` is set and the policy is [`WideIntPolicy::Reinterpret`].
+    fn next_wide_int(&mut self, unsigned: bool) -> Result<i128> {
+        match self.config.wide_ints {
+            WideIntPolicy::Reject => Ok(self.next_int()?.into()),
+            WideIntPolicy::Reinterpret if unsigned => Ok((self.next_int()? as u64).into()),
+            WideIntPolicy::Reinterpret => Ok(self.next_int()?.into()),
+            WideIntPolicy::Split => {
+                let high = self.next_int()? as u64 as u128;
+                let low = self.next_int()? as u64 as u128;
+                Ok(((high << 64) | low) as i128)
+            }
+        }
+    }
+
+    /// Reads which of `variants` variants an enum holds, according to the variant representation.
+    fn next_discriminant(&mut self, variants: usize) -> Result<u32> {
+        let index = match self.config.variants {
+            VariantRepr::Index => self.next_int()?,
+            VariantRepr::OneHot => {
+                let mut hot = None;
+                let mut set = 0;
+                for i in 0..variants {
+                    if self.next_bool()? {
+                        hot = Some(i);
+                        set += 1;
+                    }
+                }
+                match hot {
+                    Some(hot) if set == 1 => hot as i64,
+                    _ => {
+                        return Err(Error::InvalidOneHot {
+                            set,
+                            path: self.current_path(),
+                        })
+                    }
+                }
+            }
+            VariantRepr::OneHotFloat => {
+                let mut hot = None;
+                for i in 0..variants {
+                    let f = self.next_float()?;
+                    if hot.is_none_or(|(_, max)| f > max) {
+                        hot = Some((i, f));
+                    }
+                }
+                hot.map_or(-1, |(hot, _)| hot as i64)
+            }
+        };
+        match u32::try_from(index) {
+            Ok(index) if (index as usize) < variants => Ok(index),
+            _ => Err(Error::VariantOutOfRange {
+                index,
+                variants,
+                path: self.current_path(),
+            }),
+        }
+    }
+
+    /// Reads the next bool, converting it from the overridden lane if there is one.
     fn next_bool(&mut self) -> Result<bool> {
+        match self.lane {
+            None | Some(Lane::B) => self.read_bool(),
+            Some(Lane::F) => Ok(self.read_float()? >= 0.5),
+            Some(Lane::I) => Ok(self.read_int()? != 0),
+        }
+    }
+
+    fn read_bool(&mut self) -> Result<bool> {
         let b = self
-            .encoding
             .b
             .get(self.b_i)
             .copied()
-            .ok_or(Error::BoolIndexOutOfBounds)?;
+            .ok_or_else(|| self.out_of_bounds(Lane::B, self.b_i))?;
         self.b_i += 1;
         Ok(b)
     }
 }
 
-impl<'de, 'a> serde::de::Deserializer<'de> for &'a mut Deserializer<'de> {
+impl<'de> serde::de::Deserializer<'de> for &mut Deserializer<'de> {
     type Error = Error;
 
     /* Core types */
@@ -88,75 +335,92 @@ impl<'de, 'a> serde::de::Deserializer<'de> for &'a mut Deserializer<'de> {
     }
 
     /* int */
-    fn deserialize_i8<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+    fn deserialize_i8<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        unimplemented!()
+        visitor.visit_i8(self.next_int_as()?)
     }
 
-    fn deserialize_i16<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+    fn deserialize_i16<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        unimplemented!()
+        visitor.visit_i16(self.next_int_as()?)
     }
-    fn deserialize_i32<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+    fn deserialize_i32<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        unimplemented!()
+        visitor.visit_i32(self.next_int_as()?)
     }
 
-    fn deserialize_i128<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+    fn deserialize_i128<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        unimplemented!()
+        visitor.visit_i128(self.next_wide_int(false)?)
     }
 
     /* uint */
-    fn deserialize_u8<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+    fn deserialize_u8<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        unimplemented!()
+        visitor.visit_u8(self.next_int_as()?)
     }
 
-    fn deserialize_u16<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+    fn deserialize_u16<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        unimplemented!()
+        visitor.visit_u16(self.next_int_as()?)
     }
 
-    fn deserialize_u32<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+    fn deserialize_u32<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        unimplemented!()
+        visitor.visit_u32(self.next_int_as()?)
     }
 
-    fn deserialize_u64<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+    fn deserialize_u64<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        unimplemented!()
+        let v = self.next_wide_int(true)?;
+        let v = u64::try_from(v).map_err(|_| Error::IntOutOfRange {
+            value: v,
+            ty: "u64",
+            path: self.current_path(),
+        })?;
+        visitor.visit_u64(v)
     }
 
-    fn deserialize_u128<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+    fn deserialize_u128<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        unimplemented!()
+        let v = self.next_wide_int(true)?;
+        match self.config.wide_ints {
+            WideIntPolicy::Split => visitor.visit_u128(v as u128),
+            _ => {
+                let v = u128::try_from(v).map_err(|_| Error::IntOutOfRange {
+                    value: v,
+                    ty: "u128",
+                    path: self.current_path(),
+                })?;
+                visitor.visit_u128(v)
+            }
+        }
     }
 
     /* float */
-    fn deserialize_f32<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+    fn deserialize_f32<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        unimplemented!()
+        visitor.visit_f32(self.next_f32()?)
     }
 
     /* strings */
@@ -164,21 +428,21 @@ impl<'de, 'a> serde::de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: serde::de::Visitor<'de>,
     {
-        unimplemented!()
+        Err(self.unsupported("str"))
     }
 
     fn deserialize_string<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        unimplemented!()
+        Err(self.unsupported("string"))
     }
 
     fn deserialize_char<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        unimplemented!()
+        Err(self.unsupported("char"))
     }
 
     /* bytes */
@@ -186,38 +450,73 @@ impl<'de, 'a> serde::de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: serde::de::Visitor<'de>,
     {
-        unimplemented!()
+        Err(self.unsupported("byte buf"))
     }
 
     fn deserialize_bytes<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        unimplemented!()
+        Err(self.unsupported("bytes"))
     }
 
     /* option */
-    fn deserialize_option<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+    fn deserialize_option<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        unimplemented!()
+        if self.next_bool()? {
+            self.path.push(Segment::Some);
+            let value = visitor.visit_some(&mut *self)?;
+            self.path.pop();
+            Ok(value)
+        } else {
+            let width = match self.current_shape() {
+                Some(Shape::Option(inner)) => inner.width(&self.config),
+                _ => None,
+            };
+            if let Some(width) = width {
+                self.skip(width)?;
+            }
+            visitor.visit_none()
+        }
     }
 
     /* () */
-    fn deserialize_unit<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+    fn deserialize_unit<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        unimplemented!()
+        visitor.visit_unit()
     }
 
     /* list / sequence */
-    fn deserialize_seq<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+    fn deserialize_seq<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        unimplemented!()
+        let len: usize = self.next_int_as()?;
+        if let Some(max_len) = self.config.max_seq_len {
+            if len > max_len {
+                return Err(serde::de::Error::invalid_length(
+                    len,
+                    &"a sequence no longer than max_seq_len",
+                ));
+            }
+        }
+
+        let value = visitor.visit_seq(Fields::new(self, len, None))?;
+
+        if let Some(max_len) = self.config.max_seq_len {
+            let width = match self.current_shape() {
+                Some(Shape::Seq(element)) => element.width(&self.config),
+                _ => None,
+            };
+            if let Some(width) = width {
+                self.skip(width * (max_len - len))?;
+            }
+        }
+        Ok(value)
     }
 
     /* tuple */
@@ -229,7 +528,7 @@ impl<'de, 'a> serde::de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: serde::de::Visitor<'de>,
     {
-        let fields = Fields::new(self, len);
+        let fields = Fields::new(self, len, None);
         visitor.visit_seq(fields)
     }
 
@@ -238,7 +537,7 @@ impl<'de, 'a> serde::de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: serde::de::Visitor<'de>,
     {
-        unimplemented!()
+        Err(self.unsupported("map"))
     }
 
     /* struct */
@@ -251,54 +550,75 @@ impl<'de, 'a> serde::de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: serde::de::Visitor<'de>,
     {
-        let fields = Fields::new(self, fields.len());
+        let fields = Fields::new(self, fields.len(), Some(fields));
         visitor.visit_seq(fields)
     }
 
     fn deserialize_unit_struct<V>(
         self,
         _name: &'static str,
-        _visitor: V,
+        visitor: V,
     ) -> std::result::Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        todo!("unit struct")
+        visitor.visit_unit()
     }
 
     fn deserialize_newtype_struct<V>(
         self,
-        _name: &'static str,
-        _visitor: V,
+        name: &'static str,
+        visitor: V,
     ) -> std::result::Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        todo!("newtupe struct")
+        match Override::from_name(name) {
+            None => visitor.visit_newtype_struct(self),
+            Some(Override::Variants(variants)) => {
+                let outer = self.config.variants;
+                self.config.variants = variants;
+                let value = visitor.visit_newtype_struct(&mut *self);
+                self.config.variants = outer;
+                value
+            }
+            Some(Override::Lane(lane)) => {
+                let outer = self.lane;
+                self.lane = outer.or(Some(lane));
+                let value = visitor.visit_newtype_struct(&mut *self);
+                self.lane = outer;
+                value
+            }
+        }
     }
 
     fn deserialize_tuple_struct<V>(
         self,
         _name: &'static str,
-        _len: usize,
-        _visitor: V,
+        len: usize,
+        visitor: V,
     ) -> std::result::Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        todo!("tuple struct")
+        self.deserialize_tuple(len, visitor)
     }
 
     fn deserialize_enum<V>(
         self,
         _name: &'static str,
-        _variants: &'static [&'static str],
-        _visitor: V,
+        variants: &'static [&'static str],
+        visitor: V,
     ) -> std::result::Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        unimplemented!()
+        let index = self.next_discriminant(variants.len())?;
+        visitor.visit_enum(Variant {
+            de: self,
+            index,
+            name: variants[index as usize],
+        })
     }
 
     /* identifier */
@@ -306,7 +626,7 @@ impl<'de, 'a> serde::de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: serde::de::Visitor<'de>,
     {
-        unimplemented!()
+        Err(self.unsupported("identifier"))
     }
 
     /* any */
@@ -314,14 +634,14 @@ impl<'de, 'a> serde::de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: serde::de::Visitor<'de>,
     {
-        unimplemented!()
+        Err(self.unsupported("any"))
     }
 
     fn deserialize_ignored_any<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        unimplemented!()
+        Err(self.unsupported("ignored any"))
     }
 }
 
@@ -329,14 +649,21 @@ struct Fields<'a, 'de: 'a> {
     de: &'a mut Deserializer<'de>,
     num_fields: usize,
     i: usize,
+    /// The names of the fields, if they belong to a struct.
+    names: Option<&'static [&'static str]>,
 }
 
 impl<'a, 'de> Fields<'a, 'de> {
-    fn new(de: &'a mut Deserializer<'de>, num_fields: usize) -> Self {
+    fn new(
+        de: &'a mut Deserializer<'de>,
+        num_fields: usize,
+        names: Option<&'static [&'static str]>,
+    ) -> Self {
         Fields {
             de,
             num_fields,
             i: 0,
+            names,
         }
     }
 }
@@ -352,7 +679,85 @@ impl<'de, 'a> SeqAccess<'de> for Fields<'a, 'de> {
             return Ok(None);
         }
 
-        seed.deserialize(&mut *self.de).map(Some)
+        self.de.path.push(element_segment(self.i, self.names));
+        let value = seed.deserialize(&mut *self.de)?;
+        self.de.path.pop();
+        self.i += 1;
+        Ok(Some(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.num_fields - self.i)
+    }
+}
+
+/// An enum whose variant has already been read.
+struct Variant<'a, 'de: 'a> {
+    de: &'a mut Deserializer<'de>,
+    index: u32,
+    name: &'static str,
+}
+
+impl<'a, 'de> Variant<'a, 'de> {
+    /// Runs `f` on the variant's payload, then skips over the padding that follows it.
+    fn payload<F, T>(self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Deserializer<'de>) -> Result<T>,
+    {
+        self.de.path.push(Segment::Variant(self.index, self.name));
+        let value = f(&mut *self.de)?;
+        self.de.path.pop();
+
+        let width = self
+            .de
+            .current_shape()
+            .and_then(|shape| shape.variant_padding(self.index, &self.de.config));
+        if let Some(width) = width {
+            self.de.skip(width)?;
+        }
+        Ok(value)
+    }
+}
+
+impl<'de, 'a> EnumAccess<'de> for Variant<'a, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(self.index.into_deserializer())?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, 'a> VariantAccess<'de> for Variant<'a, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        self.payload(|_| Ok(()))
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        self.payload(|de| seed.deserialize(de))
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.payload(|de| visitor.visit_seq(Fields::new(de, len, None)))
+    }
+
+    fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.payload(|de| visitor.visit_seq(Fields::new(de, fields.len(), Some(fields))))
     }
 }
 
@@ -361,7 +766,76 @@ mod tests {
     use approx::assert_relative_eq;
     use serde::Deserialize;
 
-    use crate::{decode, Encoding};
+    use super::Deserializer;
+    use crate::{
+        config::{Config, F32Policy, VariantRepr},
+        decode, decode_slices, decode_slices_with_config, decode_with_config, encode_padded,
+        encode_padded_with_config,
+        error::Error,
+        Encoding, Lane,
+    };
+
+    #[test]
+    fn out_of_bounds() {
+        #[derive(Debug, Deserialize)]
+        struct Bar {
+            c: f64,
+            d: (i64, bool),
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct Foo {
+            a: i64,
+            bar: Bar,
+        }
+
+        let encoding = Encoding {
+            f: vec![1.0],
+            i: vec![2, 3],
+            b: vec![],
+        };
+
+        let err = decode::<Foo>(&encoding).unwrap_err();
+        assert!(matches!(
+            &err,
+            Error::IndexOutOfBounds {
+                lane: Lane::B,
+                index: 0,
+                len: 0,
+                path,
+            } if path == "bar.d.1"
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Index 0 is out of bounds for the b lane of 0 values at `bar.d.1`"
+        );
+
+        let encoding = Encoding {
+            f: vec![1.0],
+            i: vec![2, 3, 4],
+            b: vec![true],
+        };
+
+        assert!(matches!(
+            decode::<Foo>(&encoding),
+            Err(Error::Incomplete {
+                lane: Lane::I,
+                index: 2,
+                len: 3,
+            })
+        ));
+
+        let encoding = Encoding {
+            f: vec![1.0],
+            i: vec![2, 3],
+            b: vec![true],
+        };
+
+        let foo: Foo = decode(&encoding).unwrap();
+        assert_eq!(foo.a, 2);
+        assert_eq!(foo.bar.c, 1.0);
+        assert_eq!(foo.bar.d, (3, true));
+    }
 
     #[test]
     fn primitives() {
@@ -446,4 +920,246 @@ mod tests {
         assert_eq!(foo.bar.d.1, 9);
         assert!(foo.c);
     }
+
+    #[test]
+    fn integers() {
+        #[derive(Deserialize)]
+        struct Foo {
+            a: i8,
+            b: u16,
+            c: u32,
+            d: usize,
+        }
+
+        let encoding = Encoding {
+            f: vec![],
+            i: vec![-1, 2, 3, 4],
+            b: vec![],
+        };
+
+        let foo: Foo = decode(&encoding).unwrap();
+        assert_eq!(foo.a, -1);
+        assert_eq!(foo.b, 2);
+        assert_eq!(foo.c, 3);
+        assert_eq!(foo.d, 4);
+    }
+
+    #[test]
+    fn int_out_of_range() {
+        let encoding = Encoding {
+            f: vec![],
+            i: vec![-1],
+            b: vec![],
+        };
+
+        assert!(matches!(
+            decode::<u16>(&encoding),
+            Err(Error::IntOutOfRange {
+                value: -1,
+                ty: "u16",
+                ..
+            })
+        ));
+
+        let encoding = Encoding {
+            f: vec![],
+            i: vec![128],
+            b: vec![],
+        };
+
+        assert!(matches!(
+            decode::<i8>(&encoding),
+            Err(Error::IntOutOfRange {
+                value: 128,
+                ty: "i8",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn f32_policies() {
+        let decode_f32 = |f: f64, f32s: F32Policy| {
+            let encoding = Encoding {
+                f: vec![f],
+                i: vec![],
+                b: vec![],
+            };
+            let config = Config {
+                f32s,
+                ..Default::default()
+            };
+            decode_with_config::<f32>(&encoding, config)
+        };
+
+        assert_relative_eq!(decode_f32(0.1, F32Policy::Cast).unwrap(), 0.1);
+        assert_relative_eq!(decode_f32(0.5, F32Policy::Exact).unwrap(), 0.5);
+        assert!(matches!(
+            decode_f32(0.1, F32Policy::Exact),
+            Err(Error::FloatPrecisionLoss { .. })
+        ));
+
+        assert!(matches!(
+            decode_f32(1e300, F32Policy::Cast),
+            Err(Error::FloatOverflow { .. })
+        ));
+        assert_eq!(decode_f32(1e300, F32Policy::Saturate).unwrap(), f32::MAX);
+        assert_eq!(decode_f32(-1e300, F32Policy::Saturate).unwrap(), f32::MIN);
+        assert_eq!(
+            decode_f32(f64::INFINITY, F32Policy::Exact).unwrap(),
+            f32::INFINITY
+        );
+    }
+
+    #[test]
+    fn sequences() {
+        #[derive(Deserialize)]
+        struct Foo {
+            a: Vec<f64>,
+            b: [i64; 2],
+            c: Vec<bool>,
+        }
+
+        let encoding = Encoding {
+            f: vec![1.0, 2.0],
+            i: vec![2, 3, 4, 0],
+            b: vec![],
+        };
+
+        let foo: Foo = decode(&encoding).unwrap();
+        assert_eq!(foo.a, vec![1.0, 2.0]);
+        assert_eq!(foo.b, [3, 4]);
+        assert!(foo.c.is_empty());
+    }
+
+    #[test]
+    fn unit_variants() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        enum Color {
+            Red,
+            Green,
+            Blue,
+        }
+
+        let encoding = Encoding {
+            f: vec![],
+            i: vec![3],
+            b: vec![],
+        };
+
+        assert!(matches!(
+            decode::<Color>(&encoding),
+            Err(Error::VariantOutOfRange {
+                index: 3,
+                variants: 3,
+                ..
+            })
+        ));
+
+        let config = Config {
+            variants: VariantRepr::OneHot,
+            ..Default::default()
+        };
+
+        let encoding = Encoding {
+            f: vec![],
+            i: vec![],
+            b: vec![true, false, true],
+        };
+
+        assert!(matches!(
+            decode_with_config::<Color>(&encoding, config),
+            Err(Error::InvalidOneHot { set: 2, .. })
+        ));
+
+        let config = Config {
+            variants: VariantRepr::OneHotFloat,
+            ..Default::default()
+        };
+
+        let encoding = Encoding {
+            f: vec![0.2, 0.1, 0.7],
+            i: vec![],
+            b: vec![],
+        };
+
+        assert_eq!(
+            decode_with_config::<Color>(&encoding, config).unwrap(),
+            Color::Blue
+        );
+    }
+
+    #[test]
+    fn slices() {
+        #[derive(Debug, PartialEq, Deserialize, serde::Serialize)]
+        struct Foo {
+            a: Vec<f64>,
+            b: Option<(i64, bool)>,
+        }
+
+        // rows of a matrix are decoded in place
+        let config = Config {
+            max_seq_len: Some(2),
+            ..Default::default()
+        };
+        let matrix = [1.0, 2.0, 3.0, 0.0];
+        let ints = [2, 7, 1, 0];
+        let bools = [true, false, false, false];
+        let rows: Vec<Foo> = matrix
+            .chunks(2)
+            .zip(ints.chunks(2))
+            .zip(bools.chunks(2))
+            .map(|((f, i), b)| decode_slices_with_config(f, i, b, config).unwrap())
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                Foo {
+                    a: vec![1.0, 2.0],
+                    b: Some((7, false)),
+                },
+                Foo {
+                    a: vec![3.0],
+                    b: None,
+                },
+            ]
+        );
+        let encoding = encode_padded_with_config(&rows[1], config).unwrap();
+        assert_eq!(encoding.f, matrix[2..]);
+
+        // a deserializer reading a row needs the shape to skip the padding of `b`
+        let mut deserializer = Deserializer::from_slices(&matrix[2..], &ints[2..], &bools[2..])
+            .with_config(config)
+            .with_shape_of::<Foo>()
+            .unwrap();
+        assert_eq!(Foo::deserialize(&mut deserializer).unwrap(), rows[1]);
+        deserializer.end().unwrap();
+
+        // trailing values are an error, as they are for an encoding
+        assert!(matches!(
+            decode_slices::<(f64, i64)>(&[1.0], &[2, 3], &[]),
+            Err(Error::Incomplete {
+                lane: Lane::I,
+                index: 1,
+                len: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn with_shape_of() {
+        #[derive(Debug, PartialEq, Deserialize, serde::Serialize)]
+        struct Foo {
+            a: Option<(i64, bool)>,
+            b: i64,
+        }
+
+        let foo = Foo { a: None, b: 7 };
+        let encoding = encode_padded(&foo).unwrap();
+        let mut deserializer = Deserializer::from_encoding(&encoding)
+            .with_shape_of::<Foo>()
+            .unwrap();
+        assert_eq!(Foo::deserialize(&mut deserializer).unwrap(), foo);
+        deserializer.end().unwrap();
+    }
 }
@@ -0,0 +1,146 @@
+use super::{
+    error::{Error, Result},
+    Lane,
+};
+
+/// A type the values of a lane can be stored as instead of the lane's native type, which is
+/// `f64` for the `f` lane, `i64` for the `i` lane and `bool` for the `b` lane.
+///
+/// Conversions are checked: a value that doesn't fit, such as a finite `f64` beyond the range of
+/// `f32` or an `i64` beyond the range of `i32`, is rejected rather than made infinite or wrapped.
+/// Bools are stored as `0` and `1`, and only those two values are read back.
+pub trait Element<N>: Copy {
+    /// Converts a native value, if it fits.
+    fn from_native(value: N) -> Option<Self>;
+
+    /// Converts back to a native value, if this value stands for one.
+    fn to_native(self) -> Option<N>;
+}
+
+impl Element<f64> for f64 {
+    fn from_native(value: f64) -> Option<Self> {
+        Some(value)
+    }
+
+    fn to_native(self) -> Option<f64> {
+        Some(self)
+    }
+}
+
+impl Element<f64> for f32 {
+    fn from_native(value: f64) -> Option<Self> {
+        let narrowed = value as f32;
+        // infinities and NaNs are kept, finite values must stay finite
+        (narrowed.is_finite() || !value.is_finite()).then_some(narrowed)
+    }
+
+    fn to_native(self) -> Option<f64> {
+        Some(self.into())
+    }
+}
+
+macro_rules! impl_ints {
+    ($($ty:ty),*) => {
+        $(
+            impl Element<i64> for $ty {
+                fn from_native(value: i64) -> Option<Self> {
+                    value.try_into().ok()
+                }
+
+                fn to_native(self) -> Option<i64> {
+                    self.try_into().ok()
+                }
+            }
+        )*
+    };
+}
+
+impl_ints!(i8, i16, i32, i64, u8, u16, u32, u64);
+
+macro_rules! impl_bools {
+    ($($ty:ty => $zero:literal, $one:literal);*) => {
+        $(
+            impl Element<bool> for $ty {
+                fn from_native(value: bool) -> Option<Self> {
+                    Some(if value { $one } else { $zero })
+                }
+
+                #[allow(clippy::float_cmp)]
+                fn to_native(self) -> Option<bool> {
+                    if self == $zero {
+                        Some(false)
+                    } else if self == $one {
+                        Some(true)
+                    } else {
+                        None
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_bools!(
+    u8 => 0, 1;
+    i8 => 0, 1;
+    i32 => 0, 1;
+    i64 => 0, 1;
+    f32 => 0.0, 1.0;
+    f64 => 0.0, 1.0
+);
+
+impl Element<bool> for bool {
+    fn from_native(value: bool) -> Option<Self> {
+        Some(value)
+    }
+
+    fn to_native(self) -> Option<bool> {
+        Some(self)
+    }
+}
+
+/// Converts every value of `lane` with `convert`, failing at the first one that doesn't fit in
+/// `U`.
+pub(crate) fn convert_lane<T: Copy, U>(
+    lane: Lane,
+    values: &[T],
+    convert: impl Fn(T) -> Option<U>,
+    ty: &'static str,
+) -> Result<Vec<U>> {
+    values
+        .iter()
+        .enumerate()
+        .map(|(index, value)| {
+            convert(*value).ok_or(Error::ElementOutOfRange {
+                lane,
+                index,
+                ty,
+                path: String::new(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Element;
+
+    #[test]
+    fn checked_conversions() {
+        assert_eq!(f32::from_native(1.5), Some(1.5));
+        assert_eq!(f32::from_native(1e300), None);
+        assert_eq!(f32::from_native(f64::NEG_INFINITY), Some(f32::NEG_INFINITY));
+        assert!(f32::from_native(f64::NAN).unwrap().is_nan());
+
+        assert_eq!(<i32 as Element<i64>>::from_native(-3), Some(-3));
+        assert_eq!(<i32 as Element<i64>>::from_native(1 << 40), None);
+        assert_eq!(<u8 as Element<i64>>::from_native(-1), None);
+        assert_eq!(<u64 as Element<i64>>::to_native(u64::MAX), None);
+
+        assert_eq!(<u8 as Element<bool>>::from_native(true), Some(1));
+        assert_eq!(<u8 as Element<bool>>::to_native(0), Some(false));
+        assert_eq!(<u8 as Element<bool>>::to_native(2), None);
+        assert_eq!(<f32 as Element<bool>>::to_native(1.0), Some(true));
+        assert_eq!(<f32 as Element<bool>>::to_native(0.5), None);
+    }
+}
@@ -0,0 +1,258 @@
+use std::marker::PhantomData;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    attrs::{InLane, OneHot},
+    config::{Config, VariantRepr},
+    encode_padded_with_config,
+};
+
+#[cfg(feature = "derive")]
+pub use encodable_derive::EncodedShape;
+
+/// The lengths of the lanes of a type's encoding, known at compile time.
+///
+/// The lengths are those of the default [`Config`], and are only defined for types that are
+/// always encoded with the same number of values, so not for sequences such as `Vec<T>`.
+/// Structs and enums can derive this trait with `#[derive(EncodedShape)]`, which follows the
+/// `#[encodable(...)]` attributes of their fields.
+///
+/// Example:
+/// ```rust
+/// use encodable::encoded_shape::EncodedShape;
+///
+/// type Foo = (f64, Option<[i64; 2]>);
+///
+/// let buffer = [0.0f32; <Foo as EncodedShape>::F_LEN];
+/// assert_eq!(buffer.len(), 1);
+/// assert_eq!(<Foo as EncodedShape>::I_LEN, 2);
+/// assert_eq!(<Foo as EncodedShape>::B_LEN, 1);
+/// ```
+pub trait EncodedShape {
+    /// The length of [`Encoding::f`](crate::Encoding::f).
+    const F_LEN: usize;
+    /// The length of [`Encoding::i`](crate::Encoding::i).
+    const I_LEN: usize;
+    /// The length of [`Encoding::b`](crate::Encoding::b).
+    const B_LEN: usize;
+    /// The lengths of the `f`, `i` and `b` lanes when enums are stored as one-hot bools, which
+    /// `#[encodable(one_hot)]` fields need.
+    #[doc(hidden)]
+    const ONE_HOT_LENS: [usize; 3] = [Self::F_LEN, Self::I_LEN, Self::B_LEN];
+}
+
+/// The lengths of the `f`, `i` and `b` lanes of `T`.
+#[doc(hidden)]
+pub const fn lens<T: EncodedShape + ?Sized>() -> [usize; 3] {
+    [T::F_LEN, T::I_LEN, T::B_LEN]
+}
+
+#[doc(hidden)]
+pub const fn add(a: [usize; 3], b: [usize; 3]) -> [usize; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+#[doc(hidden)]
+pub const fn mul(a: [usize; 3], n: usize) -> [usize; 3] {
+    [a[0] * n, a[1] * n, a[2] * n]
+}
+
+/// The larger of each lane's lengths, which is the length of the region shared by the payloads
+/// of an enum's variants.
+#[doc(hidden)]
+pub const fn max(a: [usize; 3], b: [usize; 3]) -> [usize; 3] {
+    const fn max(a: usize, b: usize) -> usize {
+        if a > b {
+            a
+        } else {
+            b
+        }
+    }
+    [max(a[0], b[0]), max(a[1], b[1]), max(a[2], b[2])]
+}
+
+/// The lengths of the same values when they are all stored in `lane`, one of `'f'`, `'i'` or
+/// `'b'`.
+#[doc(hidden)]
+pub const fn in_lane(a: [usize; 3], lane: char) -> [usize; 3] {
+    let total = a[0] + a[1] + a[2];
+    match lane {
+        'f' => [total, 0, 0],
+        'i' => [0, total, 0],
+        'b' => [0, 0, total],
+        _ => panic!("a lane is one of f, i or b"),
+    }
+}
+
+/// Panics if encoding `value` doesn't produce lanes of the lengths given by its
+/// [`EncodedShape`], both with the default config and with one-hot variants. Meant to be called
+/// from the tests of types that implement the trait by hand.
+pub fn assert_encoded_shape<'de, T>(value: &T)
+where
+    T: EncodedShape + Serialize + Deserialize<'de>,
+{
+    let one_hot = Config {
+        variants: VariantRepr::OneHot,
+        ..Default::default()
+    };
+    for (config, expected) in [(Config::default(), lens::<T>()), (one_hot, T::ONE_HOT_LENS)] {
+        let encoding = encode_padded_with_config(value, config)
+            .unwrap_or_else(|err| panic!("failed to encode the value: {err}"));
+        let actual = [encoding.f.len(), encoding.i.len(), encoding.b.len()];
+        assert_eq!(
+            actual,
+            expected,
+            "the lanes of `{}` have lengths (f, i, b) = {actual:?} with {:?} variants, but its \
+             EncodedShape says {expected:?}",
+            std::any::type_name::<T>(),
+            config.variants,
+        );
+    }
+}
+
+macro_rules! impl_primitives {
+    ($lens:expr => $($ty:ty),*) => {
+        $(
+            impl EncodedShape for $ty {
+                const F_LEN: usize = $lens[0];
+                const I_LEN: usize = $lens[1];
+                const B_LEN: usize = $lens[2];
+            }
+        )*
+    };
+}
+
+impl_primitives!([1, 0, 0] => f32, f64);
+impl_primitives!([0, 1, 0] => i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+impl_primitives!([0, 0, 1] => bool);
+impl_primitives!([0, 0, 0] => ());
+
+impl<T> EncodedShape for PhantomData<T> {
+    const F_LEN: usize = 0;
+    const I_LEN: usize = 0;
+    const B_LEN: usize = 0;
+}
+
+impl<T: EncodedShape + ?Sized> EncodedShape for Box<T> {
+    const F_LEN: usize = T::F_LEN;
+    const I_LEN: usize = T::I_LEN;
+    const B_LEN: usize = T::B_LEN;
+    const ONE_HOT_LENS: [usize; 3] = T::ONE_HOT_LENS;
+}
+
+impl<T: EncodedShape> EncodedShape for Option<T> {
+    const F_LEN: usize = T::F_LEN;
+    const I_LEN: usize = T::I_LEN;
+    const B_LEN: usize = T::B_LEN + 1;
+    const ONE_HOT_LENS: [usize; 3] = add(T::ONE_HOT_LENS, [0, 0, 1]);
+}
+
+impl<T: EncodedShape, const N: usize> EncodedShape for [T; N] {
+    const F_LEN: usize = T::F_LEN * N;
+    const I_LEN: usize = T::I_LEN * N;
+    const B_LEN: usize = T::B_LEN * N;
+    const ONE_HOT_LENS: [usize; 3] = mul(T::ONE_HOT_LENS, N);
+}
+
+impl<T: EncodedShape> EncodedShape for OneHot<T> {
+    const F_LEN: usize = T::ONE_HOT_LENS[0];
+    const I_LEN: usize = T::ONE_HOT_LENS[1];
+    const B_LEN: usize = T::ONE_HOT_LENS[2];
+}
+
+impl<T: EncodedShape, const LANE: char> EncodedShape for InLane<T, LANE> {
+    const F_LEN: usize = in_lane(lens::<T>(), LANE)[0];
+    const I_LEN: usize = in_lane(lens::<T>(), LANE)[1];
+    const B_LEN: usize = in_lane(lens::<T>(), LANE)[2];
+    const ONE_HOT_LENS: [usize; 3] = in_lane(T::ONE_HOT_LENS, LANE);
+}
+
+macro_rules! impl_tuples {
+    ($(($($name:ident),+))*) => {
+        $(
+            impl<$($name: EncodedShape),+> EncodedShape for ($($name,)+) {
+                const F_LEN: usize = 0 $(+ $name::F_LEN)+;
+                const I_LEN: usize = 0 $(+ $name::I_LEN)+;
+                const B_LEN: usize = 0 $(+ $name::B_LEN)+;
+                const ONE_HOT_LENS: [usize; 3] = {
+                    let lens = [0; 3];
+                    $(let lens = add(lens, $name::ONE_HOT_LENS);)+
+                    lens
+                };
+            }
+        )*
+    };
+}
+
+impl_tuples! {
+    (A)
+    (A, B)
+    (A, B, C)
+    (A, B, C, D)
+    (A, B, C, D, E)
+    (A, B, C, D, E, F)
+    (A, B, C, D, E, F, G)
+    (A, B, C, D, E, F, G, H)
+    (A, B, C, D, E, F, G, H, I)
+    (A, B, C, D, E, F, G, H, I, J)
+    (A, B, C, D, E, F, G, H, I, J, K)
+    (A, B, C, D, E, F, G, H, I, J, K, L)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::{add, assert_encoded_shape, max, EncodedShape};
+
+    #[derive(Serialize, Deserialize)]
+    enum Action {
+        Move { dx: f64, dy: f64 },
+        Jump(i64),
+        Wait,
+    }
+
+    impl EncodedShape for Action {
+        const F_LEN: usize = 2;
+        const I_LEN: usize = 2;
+        const B_LEN: usize = 0;
+        const ONE_HOT_LENS: [usize; 3] = add([0, 0, 3], max([2, 0, 0], [0, 1, 0]));
+    }
+
+    #[test]
+    fn primitives_and_containers() {
+        type Foo = (f64, Option<[u8; 3]>, bool, ());
+        assert_eq!(<Foo as EncodedShape>::F_LEN, 1);
+        assert_eq!(<Foo as EncodedShape>::I_LEN, 3);
+        assert_eq!(<Foo as EncodedShape>::B_LEN, 2);
+        assert_encoded_shape(&(1.0, Some([1u8, 2, 3]), true, ()));
+        assert_encoded_shape::<Foo>(&(1.0, None, true, ()));
+    }
+
+    #[test]
+    fn enums() {
+        let actions = [Action::Wait, Action::Jump(1)];
+        assert_eq!(
+            <[Option<Action>; 2] as EncodedShape>::ONE_HOT_LENS,
+            [4, 2, 8]
+        );
+        assert_encoded_shape(&actions);
+        assert_encoded_shape(&Some(Action::Move { dx: 1.0, dy: 2.0 }));
+    }
+
+    #[test]
+    #[should_panic(expected = "EncodedShape says")]
+    fn mismatch() {
+        #[derive(Serialize, Deserialize)]
+        struct Wrong(f64, f64);
+
+        impl EncodedShape for Wrong {
+            const F_LEN: usize = 1;
+            const I_LEN: usize = 0;
+            const B_LEN: usize = 0;
+        }
+
+        assert_encoded_shape(&Wrong(1.0, 2.0));
+    }
+}
@@ -1,21 +1,114 @@
+use super::Lane;
+
 pub type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-    #[error("Float index out of bounds,")]
-    FloatIndexOutOfBounds,
-    #[error("Int index out of bounds,")]
-    IntIndexOutOfBounds,
-    #[error("Bool index out of bounds,")]
-    BoolIndexOutOfBounds,
-    #[error("The encoding's variables haven't been exhasted")]
-    Incomplete,
+    #[error("Index {index} is out of bounds for the {lane} lane of {len} values{}", at(.path))]
+    IndexOutOfBounds {
+        lane: Lane,
+        index: usize,
+        len: usize,
+        path: String,
+    },
+    #[error("Int {value} does not fit in {ty}{}", at(.path))]
+    IntOutOfRange {
+        value: i128,
+        ty: &'static str,
+        path: String,
+    },
+    #[error("Int {value} does not fit in {ty}{}", at(.path))]
+    UIntOutOfRange {
+        value: u128,
+        ty: &'static str,
+        path: String,
+    },
+    #[error("Float {value} overflows f32{}", at(.path))]
+    FloatOverflow { value: f64, path: String },
+    #[error("Float {value} cannot be represented exactly as f32{}", at(.path))]
+    FloatPrecisionLoss { value: f64, path: String },
+    #[error("Variant {index} is out of range for an enum with {variants} variants{}", at(.path))]
+    VariantOutOfRange {
+        index: i64,
+        variants: usize,
+        path: String,
+    },
+    #[error("Expected exactly one variant to be set in a one-hot block, found {set}{}", at(.path))]
+    InvalidOneHot { set: usize, path: String },
+    #[error("{kind} is not supported{}", at(.path))]
+    Unsupported { kind: &'static str, path: String },
+    #[error("The sequence at `{path}` has no fixed length, set `Config::max_seq_len`")]
+    UnboundedSeq { path: String },
+    #[error("The encoding was made from a different schema{}", diverging(.field))]
+    SchemaMismatch { field: Option<String> },
+    #[error("The {lane} lane holds {len} values, but the layout expects {expected}")]
+    LayoutMismatch {
+        lane: Lane,
+        expected: usize,
+        len: usize,
+    },
+    #[error(
+        "Row {row} holds {len} values in the {lane} lane, but the rows before it hold {expected}"
+    )]
+    RowWidthMismatch {
+        row: usize,
+        lane: Lane,
+        expected: usize,
+        len: usize,
+    },
+    #[error("Row {row}: {source}")]
+    InRow {
+        row: usize,
+        #[source]
+        source: Box<Error>,
+    },
+    #[error("Value {index} of the {lane} lane does not fit in {ty}{}", at(.path))]
+    ElementOutOfRange {
+        lane: Lane,
+        index: usize,
+        ty: &'static str,
+        path: String,
+    },
+    #[error(
+        "The {lane} slice is too short: it holds {len} values, but at least {needed} are needed{}",
+        at(.path)
+    )]
+    SliceTooShort {
+        lane: Lane,
+        len: usize,
+        needed: usize,
+        path: String,
+    },
+    #[error("The encoding's variables haven't been exhausted: only {index} of the {len} values of the {lane} lane were read")]
+    Incomplete {
+        lane: Lane,
+        index: usize,
+        len: usize,
+    },
     #[error("Ser message: {0}")]
     SerMessage(String),
     #[error("De message: {0}")]
     DeMessage(String),
 }
 
+/// Formats where in a value an error occurred, unless it occurred at the root.
+fn at(path: &str) -> String {
+    if path.is_empty() {
+        String::new()
+    } else {
+        format!(" at `{path}`")
+    }
+}
+
+/// Names the first field of the decoded type that isn't in the encoding's schema, or says that the
+/// schema has more fields if the type's ends before they diverge.
+fn diverging(field: &Option<String>) -> String {
+    match field {
+        Some(field) => format!(", diverging at `{field}`"),
+        None => ", which has more fields".to_string(),
+    }
+}
+
 impl serde::ser::Error for Error {
     fn custom<T>(msg: T) -> Self
     where
@@ -0,0 +1,328 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    config::Config,
+    decode_lanes,
+    error::{Error, Result},
+    layout::{Layout, Leaf},
+    serializer::Serializer,
+    shape::Shape,
+    Encoding,
+};
+
+/// A fingerprint of the schema of an encoded type: the names, types and order of its fields.
+///
+/// Fingerprints are kept alongside encodings, in a [`Fingerprinted`], and decoding one into a type
+/// with a different schema fails with [`Error::SchemaMismatch`] instead of silently reading values
+/// into the wrong fields. [`diverging_field`] finds the first field where two schemas differ.
+///
+/// The hashes don't depend on the platform or the version of Rust, so they can be stored.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Fingerprint {
+    /// A hash of the whole schema.
+    pub hash: u64,
+    /// A hash of every value of the encoding, in the order they are written. A mismatch names
+    /// the first field where they diverge.
+    pub leaves: Vec<u64>,
+}
+
+impl Fingerprint {
+    /// The fingerprint of `T`.
+    pub fn of<'de, T>() -> Result<Self>
+    where
+        T: Deserialize<'de>,
+    {
+        Self::with_config::<T>(Config::default())
+    }
+
+    /// The fingerprint of `T` when encoded using a custom config.
+    pub fn with_config<'de, T>(config: Config) -> Result<Self>
+    where
+        T: Deserialize<'de>,
+    {
+        Self::from_shape(&Shape::trace::<T>()?, &config)
+    }
+
+    fn from_shape(shape: &Shape, config: &Config) -> Result<Self> {
+        Ok(Self::from_layout(&schema_layout(shape, config)?))
+    }
+
+    fn from_layout(layout: &Layout) -> Self {
+        let leaves: Vec<u64> = layout.leaves.iter().map(hash_leaf).collect();
+        let mut hash = Fnv::default();
+        leaves
+            .iter()
+            .for_each(|leaf| hash.write(&leaf.to_le_bytes()));
+        Self {
+            hash: hash.finish(),
+            leaves,
+        }
+    }
+
+    /// Fails with [`Error::SchemaMismatch`] if `T`, encoded using `config`, doesn't have the
+    /// schema of this fingerprint. This checks batches and streams kept in a [`Fingerprinted`]
+    /// before decoding them.
+    pub fn verify<'de, T>(&self, config: Config) -> Result<()>
+    where
+        T: Deserialize<'de>,
+    {
+        self.verify_shape(&Shape::trace::<T>()?, &config)
+    }
+
+    fn verify_shape(&self, shape: &Shape, config: &Config) -> Result<()> {
+        let layout = schema_layout(shape, config)?;
+        let expected = Self::from_layout(&layout);
+        if expected == *self {
+            return Ok(());
+        }
+
+        let diverging = self
+            .leaves
+            .iter()
+            .zip(&expected.leaves)
+            .position(|(stored, expected)| stored != expected)
+            .unwrap_or(self.leaves.len().min(expected.leaves.len()));
+        Err(Error::SchemaMismatch {
+            field: layout.leaves.get(diverging).map(Leaf::name),
+        })
+    }
+}
+
+/// An encoding kept together with the fingerprint of the type it was made from. [`Encoding`]s are
+/// made and read by [`encode_fingerprinted`] and [`decode_fingerprinted`]; other encodings, such
+/// as a [`BatchEncoding`](crate::batch::BatchEncoding), are checked with
+/// [`Fingerprint::verify`].
+///
+/// Example:
+/// ```rust
+/// use encodable::fingerprint::{decode_fingerprinted, encode_fingerprinted};
+///
+/// let encoding = encode_fingerprinted(&(1.5, 2i64)).unwrap();
+/// assert_eq!(decode_fingerprinted::<(f64, i64)>(&encoding).unwrap(), (1.5, 2));
+/// assert!(decode_fingerprinted::<(f64, u8)>(&encoding).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fingerprinted<E = Encoding> {
+    pub encoding: E,
+    pub fingerprint: Fingerprint,
+}
+
+/// Encodes a struct along with the fingerprint of its schema.
+pub fn encode_fingerprinted<'de, T>(value: &T) -> Result<Fingerprinted>
+where
+    T: Serialize + Deserialize<'de>,
+{
+    encode_fingerprinted_with_config(value, Config::default())
+}
+
+/// Encodes a struct along with the fingerprint of its schema using a custom config.
+pub fn encode_fingerprinted_with_config<'de, T>(value: &T, config: Config) -> Result<Fingerprinted>
+where
+    T: Serialize + Deserialize<'de>,
+{
+    let shape = Arc::new(Shape::trace::<T>()?);
+    let fingerprint = Fingerprint::from_shape(&shape, &config)?;
+    let mut serializer = Serializer::default().with_config(config).prepared(shape);
+    value.serialize(&mut serializer)?;
+    Ok(Fingerprinted {
+        encoding: serializer.consume(),
+        fingerprint,
+    })
+}
+
+/// Decodes a struct after checking that it has the schema the encoding was made from.
+pub fn decode_fingerprinted<'de, T>(encoding: &'de Fingerprinted) -> Result<T>
+where
+    T: Deserialize<'de>,
+{
+    decode_fingerprinted_with_config(encoding, Config::default())
+}
+
+/// Decodes a struct that was encoded using a custom config after checking that it has the schema
+/// the encoding was made from.
+pub fn decode_fingerprinted_with_config<'de, T>(
+    encoding: &'de Fingerprinted,
+    config: Config,
+) -> Result<T>
+where
+    T: Deserialize<'de>,
+{
+    let shape = Arc::new(Shape::trace::<T>()?);
+    encoding.fingerprint.verify_shape(&shape, &config)?;
+    let Fingerprinted { encoding, .. } = encoding;
+    decode_lanes(&encoding.f, &encoding.i, &encoding.b, config, shape)
+}
+
+/// The first field where the schemas of `A` and `B`, encoded using `config`, diverge, or `None`
+/// if they have the same schema. If one schema is the start of the other, this is the first
+/// field only the other one has.
+pub fn diverging_field<'a, 'b, A, B>(config: Config) -> Result<Option<String>>
+where
+    A: Deserialize<'a>,
+    B: Deserialize<'b>,
+{
+    let a = schema_layout(&Shape::trace::<A>()?, &config)?;
+    let b = schema_layout(&Shape::trace::<B>()?, &config)?;
+    let diverging = a
+        .leaves
+        .iter()
+        .zip(&b.leaves)
+        .position(|(a, b)| hash_leaf(a) != hash_leaf(b))
+        .unwrap_or(a.leaves.len().min(b.leaves.len()));
+    Ok(a.leaves
+        .get(diverging)
+        .or(b.leaves.get(diverging))
+        .map(Leaf::name))
+}
+
+/// The layout the schema is read from. Sequences without a fixed length are laid out as if they
+/// held a single element, since only the shape of their elements matters.
+fn schema_layout(shape: &Shape, config: &Config) -> Result<Layout> {
+    let config = Config {
+        max_seq_len: Some(config.max_seq_len.unwrap_or(1)),
+        ..*config
+    };
+    Layout::from_shape(shape, &config)
+}
+
+fn hash_leaf(leaf: &Leaf) -> u64 {
+    let mut hash = Fnv::default();
+    hash.write(leaf.name().as_bytes());
+    hash.write(&[0]);
+    hash.write(leaf.ty.as_bytes());
+    hash.write(&[0]);
+    hash.write(leaf.lane.to_string().as_bytes());
+    hash.finish()
+}
+
+/// The 64-bit FNV-1a hash, which unlike the hashers of the standard library is guaranteed to
+/// stay the same across releases.
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::{
+        decode_fingerprinted, decode_fingerprinted_with_config, diverging_field,
+        encode_fingerprinted, Fingerprint,
+    };
+    use crate::{config::Config, decode, error::Error};
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Foo {
+        a: f64,
+        b: i64,
+        c: (u8, bool),
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Reordered {
+        b: i64,
+        a: f64,
+        c: (u8, bool),
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Retyped {
+        a: f64,
+        b: i64,
+        c: (u16, bool),
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Truncated {
+        a: f64,
+        b: i64,
+    }
+
+    #[test]
+    fn stable() {
+        assert_eq!(
+            Fingerprint::of::<Foo>().unwrap(),
+            Fingerprint::of::<Foo>().unwrap()
+        );
+        assert_ne!(
+            Fingerprint::of::<Foo>().unwrap(),
+            Fingerprint::of::<Reordered>().unwrap()
+        );
+        assert_eq!(
+            Fingerprint::of::<f64>().unwrap().hash,
+            0x9245_50a2_1e87_89ac
+        );
+    }
+
+    #[test]
+    fn schema_mismatch() {
+        let foo = Foo {
+            a: 1.0,
+            b: 2,
+            c: (3, true),
+        };
+        let encoding = encode_fingerprinted(&foo).unwrap();
+        assert_eq!(encoding.fingerprint, Fingerprint::of::<Foo>().unwrap());
+
+        let foo_decoded: Foo = decode_fingerprinted(&encoding).unwrap();
+        assert_eq!(foo, foo_decoded);
+
+        // the lanes have the same lengths, but the fields were moved around
+        let err = decode_fingerprinted::<Reordered>(&encoding).unwrap_err();
+        assert!(matches!(&err, Error::SchemaMismatch { field: Some(field) } if field == "b"));
+        assert_eq!(
+            err.to_string(),
+            "The encoding was made from a different schema, diverging at `b`"
+        );
+        assert!(matches!(
+            decode_fingerprinted_with_config::<Retyped>(&encoding, Config::default()),
+            Err(Error::SchemaMismatch { field: Some(field) }) if field == "c.0"
+        ));
+        assert!(matches!(
+            decode_fingerprinted::<Truncated>(&encoding),
+            Err(Error::SchemaMismatch { field: None })
+        ));
+
+        // comparing the layouts of both types finds where they diverge
+        let config = Config::default();
+        assert_eq!(
+            diverging_field::<Foo, Reordered>(config)
+                .unwrap()
+                .as_deref(),
+            Some("a")
+        );
+        assert_eq!(
+            diverging_field::<Foo, Retyped>(config).unwrap().as_deref(),
+            Some("c.0")
+        );
+        assert_eq!(
+            diverging_field::<Truncated, Foo>(config)
+                .unwrap()
+                .as_deref(),
+            Some("c.0")
+        );
+        assert_eq!(diverging_field::<Foo, Foo>(config).unwrap(), None);
+
+        // without a fingerprint, nothing is checked
+        assert!(decode::<Reordered>(&encoding.encoding).is_ok());
+    }
+}
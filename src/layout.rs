@@ -0,0 +1,530 @@
+use serde::Deserialize;
+
+use super::{
+    attrs::Override,
+    config::{Config, VariantRepr},
+    error::{Error, Result},
+    shape::{display_path, Segment, Shape, Width},
+    Lane,
+};
+
+/// Where every value of a type ends up in an [`Encoding`](crate::Encoding) made with
+/// [`encode_padded`](crate::encode_padded), computed from the type alone.
+///
+/// Example:
+/// ```rust
+/// use encodable::{layout::Layout, Lane};
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Foo {
+///     a: f64,
+///     b: Option<i64>,
+/// }
+///
+/// let layout = Layout::of::<Foo>().unwrap();
+/// assert_eq!((layout.f, layout.i, layout.b), (1, 1, 1));
+/// assert_eq!(layout.leaves[2].path, "b");
+/// assert_eq!(layout.leaves[2].lane, Lane::I);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Layout {
+    /// Every value in the encoding, in the order they are written. The leaves of an enum's
+    /// variants share the same region, so their offsets overlap.
+    pub leaves: Vec<Leaf>,
+    /// The length of [`Encoding::f`](crate::Encoding::f).
+    pub f: usize,
+    /// The length of [`Encoding::i`](crate::Encoding::i).
+    pub i: usize,
+    /// The length of [`Encoding::b`](crate::Encoding::b).
+    pub b: usize,
+}
+
+/// A single value of an encoding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Leaf {
+    /// The dotted path of the field the value belongs to, such as `bar.d.1`. Values inside of an
+    /// enum's variant go through the variant's name.
+    pub path: String,
+    pub lane: Lane,
+    /// The lane the value is stored in when no lane is overridden, which tells whether it's a
+    /// float, an int or a bool, even when [`Config::all_floats`] stores it in the float lane.
+    pub source: Lane,
+    /// The index of the value within its lane.
+    pub offset: usize,
+    pub role: Role,
+    /// The type of the field, such as `u8`. Presence flags are `bool`s, lengths are `usize`s and
+    /// discriminants are `u32`s.
+    pub ty: &'static str,
+    /// The variant this leaf belongs to, if it's inside of an enum's payload. The leaf only holds
+    /// a value when the enum holds that variant.
+    pub variant: Option<VariantOf>,
+    /// The index in [`Layout::leaves`] of the presence flag of the innermost option this leaf is
+    /// inside of. The leaf only holds a value when the option holds one.
+    pub option: Option<usize>,
+}
+
+/// The variant of an enum that a [`Leaf`] belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VariantOf {
+    /// The index in [`Layout::leaves`] of the enum's first discriminant leaf.
+    pub discriminant: usize,
+    /// The index of the variant.
+    pub index: u32,
+    /// How the enum stores which variant it holds, which a field's attributes can override.
+    pub repr: VariantRepr,
+}
+
+/// What a [`Leaf`] stores about its field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Role {
+    /// The value of a primitive field.
+    Value,
+    /// The high 64 bits of a wide int split over two slots.
+    High,
+    /// The low 64 bits of a wide int split over two slots.
+    Low,
+    /// Whether an option holds a value.
+    Presence,
+    /// The length of a sequence.
+    Len,
+    /// Which variant an enum holds. With one-hot variants there is one per variant, and its path
+    /// goes through the variant's name.
+    Discriminant,
+}
+
+impl Layout {
+    /// The layout of `T`, without a value of it.
+    pub fn of<'de, T>() -> Result<Self>
+    where
+        T: Deserialize<'de>,
+    {
+        Self::with_config::<T>(Config::default())
+    }
+
+    /// The layout of `T` when encoded using a custom config.
+    pub fn with_config<'de, T>(config: Config) -> Result<Self>
+    where
+        T: Deserialize<'de>,
+    {
+        Self::from_shape(&Shape::trace::<T>()?, &config)
+    }
+
+    /// Lays out a value of `shape`. Sequences must have a fixed length, set with
+    /// [`Config::max_seq_len`].
+    pub(crate) fn from_shape(shape: &Shape, config: &Config) -> Result<Self> {
+        Self::from_shape_in_lane(shape, config, None)
+    }
+
+    /// Lays out a value of `shape` whose values are all stored in `lane`, if it's set, or else
+    /// in the float lane if [`Config::all_floats`] is set.
+    pub(crate) fn from_shape_in_lane(
+        shape: &Shape,
+        config: &Config,
+        lane: Option<Lane>,
+    ) -> Result<Self> {
+        let mut builder = Builder {
+            config: *config,
+            lane: lane.or(config.single_lane()),
+            path: Vec::new(),
+            cursor: Width::default(),
+            leaves: Vec::new(),
+            variant: None,
+            option: None,
+        };
+        builder.visit(shape)?;
+        Ok(Self {
+            leaves: builder.leaves,
+            f: builder.cursor.f,
+            i: builder.cursor.i,
+            b: builder.cursor.b,
+        })
+    }
+
+    /// The leaves stored in `lane`, in the order of their offsets.
+    pub fn lane(&self, lane: Lane) -> impl Iterator<Item = &Leaf> {
+        self.leaves.iter().filter(move |leaf| leaf.lane == lane)
+    }
+}
+
+impl Leaf {
+    /// The name of the value this leaf describes.
+    pub fn name(&self) -> String {
+        format!("{}{}", self.path, self.role.suffix())
+    }
+}
+
+impl Role {
+    /// The suffix added to the path of the values with this role.
+    pub(crate) fn suffix(self) -> &'static str {
+        match self {
+            Self::Value => "",
+            Self::High => "#hi",
+            Self::Low => "#lo",
+            Self::Presence => "#some",
+            Self::Len => "#len",
+            Self::Discriminant => "#variant",
+        }
+    }
+}
+
+/// Walks a shape while keeping track of the next free offset of every lane.
+struct Builder {
+    config: Config,
+    /// The lane every value is stored in, if it was overridden.
+    lane: Option<Lane>,
+    path: Vec<Segment>,
+    cursor: Width,
+    leaves: Vec<Leaf>,
+    /// The variant the leaves being pushed belong to.
+    variant: Option<VariantOf>,
+    /// The presence flag of the option the leaves being pushed are inside of.
+    option: Option<usize>,
+}
+
+impl Builder {
+    fn push(&mut self, source: Lane, role: Role, ty: &'static str) {
+        let lane = self.lane.unwrap_or(source);
+        let offset = match lane {
+            Lane::F => &mut self.cursor.f,
+            Lane::I => &mut self.cursor.i,
+            Lane::B => &mut self.cursor.b,
+        };
+        self.leaves.push(Leaf {
+            path: display_path(&self.path),
+            lane,
+            source,
+            offset: *offset,
+            role,
+            ty,
+            variant: self.variant,
+            option: self.option,
+        });
+        *offset += 1;
+    }
+
+    /// Visits the child found by following `segment`.
+    fn child(&mut self, segment: Segment, shape: &Shape) -> Result<()> {
+        self.path.push(segment);
+        self.visit(shape)?;
+        self.path.pop();
+        Ok(())
+    }
+
+    fn visit(&mut self, shape: &Shape) -> Result<()> {
+        match shape {
+            Shape::Primitive(primitive) => {
+                let width = primitive.width(&self.config);
+                let ty = primitive.name();
+                if width.b > 0 {
+                    self.push(Lane::B, Role::Value, ty);
+                } else if width.f > 0 {
+                    self.push(Lane::F, Role::Value, ty);
+                } else if width.i > 1 {
+                    self.push(Lane::I, Role::High, ty);
+                    self.push(Lane::I, Role::Low, ty);
+                } else {
+                    self.push(Lane::I, Role::Value, ty);
+                }
+            }
+            Shape::Unit => {}
+            Shape::Override(Override::Variants(variants), inner) => {
+                let config = self.config;
+                self.config.variants = *variants;
+                self.visit(inner)?;
+                self.config = config;
+            }
+            Shape::Override(Override::Lane(lane), inner) => {
+                // an outer override wins over the ones inside of it
+                let outer = self.lane;
+                self.lane = outer.or(Some(*lane));
+                self.visit(inner)?;
+                self.lane = outer;
+            }
+            Shape::Option(inner) => {
+                let presence = self.leaves.len();
+                self.push(Lane::B, Role::Presence, "bool");
+                let parent = self.option.replace(presence);
+                self.child(Segment::Some, inner)?;
+                self.option = parent;
+            }
+            Shape::Seq(element) => {
+                let len = self.config.max_seq_len.ok_or_else(|| Error::UnboundedSeq {
+                    path: display_path(&self.path),
+                })?;
+                self.push(Lane::I, Role::Len, "usize");
+                for i in 0..len {
+                    self.child(Segment::Index(i), element)?;
+                }
+            }
+            Shape::Tuple(elements) => {
+                for (i, element) in elements.iter().enumerate() {
+                    self.child(Segment::Index(i), element)?;
+                }
+            }
+            Shape::Struct(fields) => {
+                for (i, (name, field)) in fields.iter().enumerate() {
+                    self.child(Segment::Field(i, name), field)?;
+                }
+            }
+            Shape::Enum(variants) => {
+                let segments = variants
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (name, _))| Segment::Variant(i as u32, name));
+                let discriminant = self.leaves.len();
+                match self.config.variants {
+                    VariantRepr::Index => self.push(Lane::I, Role::Discriminant, "u32"),
+                    VariantRepr::OneHot | VariantRepr::OneHotFloat => {
+                        let lane = match self.config.variants {
+                            VariantRepr::OneHot => Lane::B,
+                            _ => Lane::F,
+                        };
+                        for segment in segments.clone() {
+                            self.path.push(segment);
+                            self.push(lane, Role::Discriminant, "u32");
+                            self.path.pop();
+                        }
+                    }
+                }
+
+                // every payload starts at the same offset, and the region is as wide as the
+                // widest one
+                let start = self.cursor;
+                let mut end = start;
+                let parent = self.variant;
+                for (index, (segment, (_, payload))) in segments.zip(variants).enumerate() {
+                    self.cursor = start;
+                    self.variant = Some(VariantOf {
+                        discriminant,
+                        index: index as u32,
+                        repr: self.config.variants,
+                    });
+                    self.child(segment, payload)?;
+                    end = end.max(self.cursor);
+                }
+                self.variant = parent;
+                // a region stored in a single lane is as wide as the region would be in all
+                // three, as variants are padded lane by lane before being moved into it
+                self.cursor = match (self.lane, shape.union_width(&self.config)) {
+                    (Some(lane), Some(union)) => start + union.in_lane(lane),
+                    _ => end,
+                };
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::{Layout, Leaf, Role, VariantOf};
+    use crate::{
+        config::{Config, VariantRepr, WideIntPolicy},
+        encode_padded_with_config,
+        error::Error,
+        Lane,
+    };
+
+    fn leaf(path: &str, lane: Lane, offset: usize, role: Role, ty: &'static str) -> Leaf {
+        Leaf {
+            path: path.to_string(),
+            lane,
+            source: lane,
+            offset,
+            role,
+            ty,
+            variant: None,
+            option: None,
+        }
+    }
+
+    fn variant_leaf(leaf: Leaf, discriminant: usize, index: u32) -> Leaf {
+        Leaf {
+            variant: Some(VariantOf {
+                discriminant,
+                index,
+                repr: VariantRepr::Index,
+            }),
+            ..leaf
+        }
+    }
+
+    #[test]
+    fn leaves() {
+        #[derive(Serialize, Deserialize)]
+        struct Bar {
+            c: bool,
+            d: (f64, i64),
+        }
+
+        #[derive(Serialize, Deserialize)]
+        struct Foo {
+            a: Option<u8>,
+            bar: Bar,
+            e: u128,
+        }
+
+        let config = Config {
+            wide_ints: WideIntPolicy::Split,
+            ..Default::default()
+        };
+        let layout = Layout::with_config::<Foo>(config).unwrap();
+
+        assert_eq!(
+            layout.leaves,
+            vec![
+                leaf("a", Lane::B, 0, Role::Presence, "bool"),
+                Leaf {
+                    option: Some(0),
+                    ..leaf("a", Lane::I, 0, Role::Value, "u8")
+                },
+                leaf("bar.c", Lane::B, 1, Role::Value, "bool"),
+                leaf("bar.d.0", Lane::F, 0, Role::Value, "f64"),
+                leaf("bar.d.1", Lane::I, 1, Role::Value, "i64"),
+                leaf("e", Lane::I, 2, Role::High, "u128"),
+                leaf("e", Lane::I, 3, Role::Low, "u128"),
+            ]
+        );
+        assert_eq!((layout.f, layout.i, layout.b), (1, 4, 2));
+        assert_eq!(
+            layout
+                .lane(Lane::B)
+                .map(|leaf| leaf.path.as_str())
+                .collect::<Vec<_>>(),
+            vec!["a", "bar.c"]
+        );
+
+        let foo = Foo {
+            a: None,
+            bar: Bar {
+                c: true,
+                d: (1.0, 2),
+            },
+            e: 3,
+        };
+        let encoding = encode_padded_with_config(&foo, config).unwrap();
+        assert_eq!(
+            (encoding.f.len(), encoding.i.len(), encoding.b.len()),
+            (layout.f, layout.i, layout.b)
+        );
+    }
+
+    #[test]
+    fn enums() {
+        #[derive(Serialize, Deserialize)]
+        enum Shape {
+            Circle(f64),
+            Rect { w: f64, h: f64 },
+            Empty,
+        }
+
+        let layout = Layout::of::<Shape>().unwrap();
+        assert_eq!(
+            layout.leaves,
+            vec![
+                leaf("", Lane::I, 0, Role::Discriminant, "u32"),
+                variant_leaf(leaf("Circle", Lane::F, 0, Role::Value, "f64"), 0, 0),
+                variant_leaf(leaf("Rect.w", Lane::F, 0, Role::Value, "f64"), 0, 1),
+                variant_leaf(leaf("Rect.h", Lane::F, 1, Role::Value, "f64"), 0, 1),
+            ]
+        );
+        assert_eq!((layout.f, layout.i, layout.b), (2, 1, 0));
+
+        let config = Config {
+            variants: VariantRepr::OneHot,
+            ..Default::default()
+        };
+        let layout = Layout::with_config::<Shape>(config).unwrap();
+        assert_eq!(
+            layout.lane(Lane::B).cloned().collect::<Vec<_>>(),
+            vec![
+                leaf("Circle", Lane::B, 0, Role::Discriminant, "u32"),
+                leaf("Rect", Lane::B, 1, Role::Discriminant, "u32"),
+                leaf("Empty", Lane::B, 2, Role::Discriminant, "u32"),
+            ]
+        );
+    }
+
+    #[test]
+    fn sequences() {
+        #[derive(Serialize, Deserialize)]
+        struct Foo {
+            a: Vec<f64>,
+            b: [bool; 2],
+        }
+
+        assert!(matches!(
+            Layout::of::<Foo>(),
+            Err(Error::UnboundedSeq { path }) if path == "a"
+        ));
+
+        let config = Config {
+            max_seq_len: Some(2),
+            ..Default::default()
+        };
+        let layout = Layout::with_config::<Foo>(config).unwrap();
+        assert_eq!(
+            layout.leaves,
+            vec![
+                leaf("a", Lane::I, 0, Role::Len, "usize"),
+                leaf("a.0", Lane::F, 0, Role::Value, "f64"),
+                leaf("a.1", Lane::F, 1, Role::Value, "f64"),
+                leaf("b.0", Lane::B, 0, Role::Value, "bool"),
+                leaf("b.1", Lane::B, 1, Role::Value, "bool"),
+            ]
+        );
+    }
+
+    #[test]
+    fn all_floats() {
+        #[derive(Serialize, Deserialize)]
+        enum Shape {
+            Circle(f64),
+            Rect(u8, u8, u8),
+        }
+
+        #[derive(Serialize, Deserialize)]
+        struct Foo {
+            a: Option<i64>,
+            b: Shape,
+        }
+
+        let config = Config {
+            all_floats: true,
+            ..Default::default()
+        };
+        let layout = Layout::with_config::<Foo>(config).unwrap();
+        let sources: Vec<_> = layout
+            .leaves
+            .iter()
+            .map(|leaf| (leaf.name(), leaf.lane, leaf.source, leaf.offset))
+            .collect();
+        assert_eq!(
+            sources,
+            vec![
+                ("a#some".to_string(), Lane::F, Lane::B, 0),
+                ("a".to_string(), Lane::F, Lane::I, 1),
+                ("b#variant".to_string(), Lane::F, Lane::I, 2),
+                ("b.Circle".to_string(), Lane::F, Lane::F, 3),
+                ("b.Rect.0".to_string(), Lane::F, Lane::I, 3),
+                ("b.Rect.1".to_string(), Lane::F, Lane::I, 4),
+                ("b.Rect.2".to_string(), Lane::F, Lane::I, 5),
+            ]
+        );
+
+        // the variants are padded in each lane, so the region holds one float and three ints
+        let foo = Foo {
+            a: None,
+            b: Shape::Circle(1.0),
+        };
+        let encoding = encode_padded_with_config(&foo, config).unwrap();
+        assert_eq!((layout.f, layout.i, layout.b), (7, 0, 0));
+        assert_eq!(
+            (encoding.f.len(), encoding.i.len(), encoding.b.len()),
+            (layout.f, layout.i, layout.b)
+        );
+    }
+}
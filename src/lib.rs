@@ -1,10 +1,33 @@
+pub mod attrs;
+pub mod batch;
+pub mod config;
 pub mod deserializer;
+pub mod element;
+pub mod encoded_shape;
 pub mod error;
+pub mod fingerprint;
+pub mod layout;
+pub mod migrate;
+pub mod names;
 pub mod serializer;
+mod shape;
+pub mod stream;
 
-use serde::{Deserialize, Serialize};
+#[cfg(feature = "derive")]
+pub use encodable_derive::{Encodable, EncodedShape};
 
-use self::{deserializer::Deserializer, error::Result, serializer::Serializer};
+use std::{any::type_name, marker::PhantomData, sync::Arc};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use self::{
+    config::Config,
+    deserializer::Deserializer,
+    element::{convert_lane, Element},
+    error::Result,
+    serializer::{Output, Serializer, Slices},
+    shape::Shape,
+};
 
 /// An encoding of a struct.
 ///
@@ -29,35 +52,459 @@ use self::{deserializer::Deserializer, error::Result, serializer::Serializer};
 /// let encoding = encode(&foo).unwrap();
 /// let foo: Foo = decode(&encoding).unwrap();
 /// ````
+pub type Encoding = EncodingOf<f64, i64, bool>;
+
+/// An encoding of a struct whose lanes hold elements of the types `F`, `I` and `B` instead of
+/// `f64`, `i64` and `bool`, such as the `f32`, `i32` and `u8` buffers that tensors are made of.
+/// See [`Element`] for how values are converted.
+///
+/// Example:
+/// ```rust
+/// use encodable::{decode_as, encode_as, EncodingOf};
+///
+/// let encoding: EncodingOf<f32, i32, u8> = encode_as(&(1.5, Some(2))).unwrap();
+/// assert_eq!((&encoding.f, &encoding.i, &encoding.b), (&vec![1.5], &vec![2], &vec![1]));
+/// let value: (f64, Option<i64>) = decode_as(&encoding).unwrap();
+/// assert_eq!(value, (1.5, Some(2)));
+///
+/// assert!(encode_as::<f32, u8, u8>(&(1.5, Some(-2))).is_err());
+/// ```
 #[derive(Debug, Clone, Default, PartialEq)]
-pub struct Encoding {
-    pub f: Vec<f64>,
-    pub i: Vec<i64>,
-    pub b: Vec<bool>,
+pub struct EncodingOf<F, I, B> {
+    pub f: Vec<F>,
+    pub i: Vec<I>,
+    pub b: Vec<B>,
+}
+
+impl Encoding {
+    /// Converts every value to the element types `F`, `I` and `B`, failing with
+    /// [`Error::ElementOutOfRange`](error::Error::ElementOutOfRange) at the first value that
+    /// doesn't fit.
+    pub fn convert<F, I, B>(&self) -> Result<EncodingOf<F, I, B>>
+    where
+        F: Element<f64>,
+        I: Element<i64>,
+        B: Element<bool>,
+    {
+        Ok(EncodingOf {
+            f: convert_lane(Lane::F, &self.f, F::from_native, type_name::<F>())?,
+            i: convert_lane(Lane::I, &self.i, I::from_native, type_name::<I>())?,
+            b: convert_lane(Lane::B, &self.b, B::from_native, type_name::<B>())?,
+        })
+    }
+}
+
+impl<F, I, B> EncodingOf<F, I, B>
+where
+    F: Element<f64>,
+    I: Element<i64>,
+    B: Element<bool>,
+{
+    /// Converts every value back to `f64`, `i64` and `bool`, failing at the first value that
+    /// doesn't stand for one, such as a bool stored as `2`.
+    pub fn to_native(&self) -> Result<Encoding> {
+        Ok(Encoding {
+            f: convert_lane(Lane::F, &self.f, F::to_native, "f64")?,
+            i: convert_lane(Lane::I, &self.i, I::to_native, "i64")?,
+            b: convert_lane(Lane::B, &self.b, B::to_native, "bool")?,
+        })
+    }
+}
+
+/// One of the three vectors of an [`Encoding`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lane {
+    /// The floats vector, [`Encoding::f`].
+    F,
+    /// The ints vector, [`Encoding::i`].
+    I,
+    /// The bools vector, [`Encoding::b`].
+    B,
+}
+
+impl std::fmt::Display for Lane {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::F => write!(f, "f"),
+            Self::I => write!(f, "i"),
+            Self::B => write!(f, "b"),
+        }
+    }
 }
 
 /// Encoding a struct
+///
+/// Absent values, such as `None`, are written without padding, so the number of values depends
+/// on the value. To always encode a type with the same number of values, use [`encode_padded`]
+/// or an [`Encoder`].
 pub fn encode<T>(value: &T) -> Result<Encoding>
 where
     T: Serialize,
 {
-    let mut serializer = Serializer::default();
+    encode_with_config(value, Config::default())
+}
+
+/// Encoding a struct using a custom config
+pub fn encode_with_config<T>(value: &T, config: Config) -> Result<Encoding>
+where
+    T: Serialize,
+{
+    encode_to(value, Serializer::default().with_config(config))
+}
+
+/// Encodes a struct, padding absent values such as `None` to the width of the values they stand
+/// in for, so that every value of `T` is encoded with the same number of values. An encoding
+/// made this way must be decoded with [`decode_padded`].
+///
+/// `T` must also implement `Deserialize` so that its shape can be traced. It is traced on every
+/// call; to encode many values, use an [`Encoder`], which traces `T` once.
+///
+/// Example:
+/// ```rust
+/// use encodable::{decode_padded, encode, encode_padded};
+///
+/// assert_eq!(encode(&(None::<f64>, 2)).unwrap().f, vec![]);
+/// let encoding = encode_padded(&(None::<f64>, 2)).unwrap();
+/// assert_eq!(encoding.f, vec![0.0]);
+/// assert_eq!(
+///     decode_padded::<(Option<f64>, i64)>(&encoding).unwrap(),
+///     (None, 2)
+/// );
+/// ```
+pub fn encode_padded<'de, T>(value: &T) -> Result<Encoding>
+where
+    T: Serialize + Deserialize<'de>,
+{
+    encode_padded_with_config(value, Config::default())
+}
+
+/// Encodes a struct using a custom config, padding absent values.
+pub fn encode_padded_with_config<'de, T>(value: &T, config: Config) -> Result<Encoding>
+where
+    T: Serialize + Deserialize<'de>,
+{
+    encode_to(
+        value,
+        Serializer::default()
+            .with_config(config)
+            .with_shape_of::<T>()?,
+    )
+}
+
+/// Encodes a struct into lanes of the element types `F`, `I` and `B`, such as `f32`, `i32` and
+/// `u8`, converting every value as it is written.
+///
+/// A value that doesn't fit in its lane's element type fails with
+/// [`Error::ElementOutOfRange`](error::Error::ElementOutOfRange).
+pub fn encode_as<F, I, B>(value: &impl Serialize) -> Result<EncodingOf<F, I, B>>
+where
+    F: Element<f64> + Default,
+    I: Element<i64> + Default,
+    B: Element<bool> + Default,
+{
+    encode_as_with_config(value, Config::default())
+}
+
+/// Encodes a struct into lanes of the element types `F`, `I` and `B` using a custom config.
+pub fn encode_as_with_config<F, I, B>(
+    value: &impl Serialize,
+    config: Config,
+) -> Result<EncodingOf<F, I, B>>
+where
+    F: Element<f64> + Default,
+    I: Element<i64> + Default,
+    B: Element<bool> + Default,
+{
+    encode_to(
+        value,
+        Serializer::default()
+            .with_config(config)
+            .with_output(EncodingOf::default()),
+    )
+}
+
+/// Encodes a struct with `serializer`, whose output is cleared, and returns the output.
+fn encode_to<T, O>(value: &T, mut serializer: Serializer<O>) -> Result<O>
+where
+    T: Serialize,
+    O: Output,
+{
     value.serialize(&mut serializer)?;
-    Ok(serializer.consume())
+    Ok(serializer.into_output())
+}
+
+/// Encodes a struct into the buffers of `encoding`, replacing its values while reusing its
+/// capacity. Like [`encode`], absent values are not padded.
+///
+/// Example:
+/// ```rust
+/// use encodable::{encode_reusing, Encoding};
+///
+/// let mut encoding = Encoding::default();
+/// encode_reusing(&mut encoding, &(1.0, 2)).unwrap();
+/// encode_reusing(&mut encoding, &(3.0, 4)).unwrap();
+/// assert_eq!((encoding.f, encoding.i), (vec![3.0], vec![4]));
+/// ```
+pub fn encode_reusing<T>(encoding: &mut Encoding, value: &T) -> Result<()>
+where
+    T: Serialize,
+{
+    encode_reusing_with_config(encoding, value, Config::default())
+}
+
+/// Encodes a struct into the buffers of `encoding` using a custom config. If encoding fails, the
+/// values of `encoding` are left unspecified.
+pub fn encode_reusing_with_config<T>(
+    encoding: &mut Encoding,
+    value: &T,
+    config: Config,
+) -> Result<()>
+where
+    T: Serialize,
+{
+    let mut serializer = Serializer::default()
+        .with_config(config)
+        .with_encoding(std::mem::take(encoding));
+    let result = value.serialize(&mut serializer);
+    *encoding = serializer.consume();
+    result
+}
+
+/// Encodes values of `T` one after the other into the same buffers. `T` is traced once, when the
+/// encoder is made, and room for a whole value is reserved if `T` is always encoded with the same
+/// number of values, so a steady-state encode allocates nothing.
+///
+/// Example:
+/// ```rust
+/// use encodable::Encoder;
+///
+/// let mut encoder = Encoder::<(f64, Option<i64>)>::new().unwrap();
+/// for step in 0..10 {
+///     let encoding = encoder.encode(&(step as f64, Some(step))).unwrap();
+///     assert_eq!(encoding.i, vec![step]);
+/// }
+/// ```
+pub struct Encoder<T> {
+    serializer: Serializer,
+    value: PhantomData<fn(&T)>,
+}
+
+impl<T: Serialize> Encoder<T> {
+    pub fn new<'de>() -> Result<Self>
+    where
+        T: Deserialize<'de>,
+    {
+        Self::with_config(Config::default())
+    }
+
+    pub fn with_config<'de>(config: Config) -> Result<Self>
+    where
+        T: Deserialize<'de>,
+    {
+        Ok(Self {
+            serializer: Serializer::default()
+                .with_config(config)
+                .with_shape_of::<T>()?,
+            value: PhantomData,
+        })
+    }
+
+    /// Encodes `value` in place of the value encoded before it. If encoding fails, the values of
+    /// the encoding are left unspecified.
+    pub fn encode(&mut self, value: &T) -> Result<&Encoding> {
+        self.serializer.reset();
+        value.serialize(&mut self.serializer)?;
+        Ok(self.serializer.encoding())
+    }
+
+    /// The encoding of the last value.
+    pub fn encoding(&self) -> &Encoding {
+        self.serializer.encoding()
+    }
+
+    pub fn into_encoding(self) -> Encoding {
+        self.serializer.consume()
+    }
+}
+
+/// Encodes a struct into caller-owned slices, one per lane, without allocating them, and
+/// returns the number of values written to the `f`, `i` and `b` slices.
+///
+/// Absent values are padded, as by [`encode_padded`]. The values are written from the start of
+/// each slice, and the rest of it is left untouched.
+/// If a slice is too short, this fails with [`Error::SliceTooShort`](error::Error::SliceTooShort)
+/// and its contents are left unspecified. `T` is traced on every call; to avoid that in a loop,
+/// give a [`Serializer`] made with [`Serializer::with_shape_of`] new slices with
+/// [`Serializer::replace_output`].
+///
+/// Example:
+/// ```rust
+/// use encodable::encode_into;
+///
+/// let (mut f, mut i, mut b) = ([0.0; 4], [0; 4], [false; 4]);
+/// let lens = encode_into(&(1.5, Some(2)), &mut f, &mut i, &mut b).unwrap();
+/// assert_eq!(lens, [1, 1, 1]);
+/// assert_eq!((f[0], i[0], b[0]), (1.5, 2, true));
+///
+/// assert!(encode_into(&(1.5, 2.5), &mut f[..1], &mut i, &mut b).is_err());
+/// ```
+pub fn encode_into<'de, T, F, I, B>(
+    value: &T,
+    f: &mut [F],
+    i: &mut [I],
+    b: &mut [B],
+) -> Result<[usize; 3]>
+where
+    T: Serialize + Deserialize<'de>,
+    F: Element<f64>,
+    I: Element<i64>,
+    B: Element<bool>,
+{
+    encode_into_with_config(value, f, i, b, Config::default())
+}
+
+/// Encodes a struct into caller-owned slices using a custom config.
+pub fn encode_into_with_config<'de, T, F, I, B>(
+    value: &T,
+    f: &mut [F],
+    i: &mut [I],
+    b: &mut [B],
+    config: Config,
+) -> Result<[usize; 3]>
+where
+    T: Serialize + Deserialize<'de>,
+    F: Element<f64>,
+    I: Element<i64>,
+    B: Element<bool>,
+{
+    let serializer = Serializer::default()
+        .with_config(config)
+        .with_output(Slices::new(f, i, b))
+        .with_shape_of::<T>()?;
+    encode_to(value, serializer).map(|slices| slices.lens())
 }
 
 /// Decoding a struct
+///
+/// The encoding must have been made without padding, such as by [`encode`]. Encodings made by
+/// [`encode_padded`] or an [`Encoder`] are decoded with [`decode_padded`].
 pub fn decode<'de, T>(encoding: &'de Encoding) -> Result<T>
 where
     T: Deserialize<'de>,
 {
-    let mut deserializer = Deserializer::from_encoding(encoding);
-    let res = T::deserialize(&mut deserializer);
-    if !deserializer.completed() {
-        Err(error::Error::Incomplete)
-    } else {
-        res
-    }
+    decode_with_config(encoding, Config::default())
+}
+
+/// Decoding a struct that was encoded using a custom config
+pub fn decode_with_config<'de, T>(encoding: &'de Encoding, config: Config) -> Result<T>
+where
+    T: Deserialize<'de>,
+{
+    let mut deserializer = Deserializer::from_encoding(encoding).with_config(config);
+    let res = T::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(res)
+}
+
+/// Decodes a struct whose absent values were padded, such as by [`encode_padded`] or an
+/// [`Encoder`]. `T` is traced on every call to know how much padding to skip.
+pub fn decode_padded<'de, T>(encoding: &'de Encoding) -> Result<T>
+where
+    T: Deserialize<'de>,
+{
+    decode_padded_with_config(encoding, Config::default())
+}
+
+/// Decodes a struct whose absent values were padded, and that was encoded using a custom config.
+pub fn decode_padded_with_config<'de, T>(encoding: &'de Encoding, config: Config) -> Result<T>
+where
+    T: Deserialize<'de>,
+{
+    let shape = Arc::new(Shape::trace::<T>()?);
+    decode_lanes(&encoding.f, &encoding.i, &encoding.b, config, shape)
+}
+
+/// Decodes a struct from lanes of the element types `F`, `I` and `B`, converting them back to
+/// `f64`, `i64` and `bool` first.
+pub fn decode_as<T, F, I, B>(encoding: &EncodingOf<F, I, B>) -> Result<T>
+where
+    T: DeserializeOwned,
+    F: Element<f64>,
+    I: Element<i64>,
+    B: Element<bool>,
+{
+    decode_as_with_config(encoding, Config::default())
+}
+
+/// Decodes a struct that was encoded using a custom config from lanes of the element types `F`,
+/// `I` and `B`.
+pub fn decode_as_with_config<T, F, I, B>(
+    encoding: &EncodingOf<F, I, B>,
+    config: Config,
+) -> Result<T>
+where
+    T: DeserializeOwned,
+    F: Element<f64>,
+    I: Element<i64>,
+    B: Element<bool>,
+{
+    decode_with_config(&encoding.to_native()?, config)
+}
+
+/// Decodes a struct from borrowed slices, one per lane, without copying them into an
+/// [`Encoding`].
+///
+/// The slices must hold exactly the values of one padded encoding, such as the lengths returned
+/// by [`encode_into`], otherwise this fails with [`Error::Incomplete`](error::Error::Incomplete).
+/// Like [`decode_padded`], `T` is traced on every call.
+///
+/// Example:
+/// ```rust
+/// use encodable::{decode_slices, encode_into};
+///
+/// let (mut f, mut i, mut b) = ([0.0; 4], [0; 4], [false; 4]);
+/// let [f_len, i_len, b_len] = encode_into(&(1.5, Some(2)), &mut f, &mut i, &mut b).unwrap();
+/// let value: (f64, Option<i64>) = decode_slices(&f[..f_len], &i[..i_len], &b[..b_len]).unwrap();
+/// assert_eq!(value, (1.5, Some(2)));
+/// ```
+pub fn decode_slices<'de, T>(f: &'de [f64], i: &'de [i64], b: &'de [bool]) -> Result<T>
+where
+    T: Deserialize<'de>,
+{
+    decode_slices_with_config(f, i, b, Config::default())
+}
+
+/// Decodes a struct that was encoded using a custom config from borrowed slices.
+pub fn decode_slices_with_config<'de, T>(
+    f: &'de [f64],
+    i: &'de [i64],
+    b: &'de [bool],
+    config: Config,
+) -> Result<T>
+where
+    T: Deserialize<'de>,
+{
+    let shape = Arc::new(Shape::trace::<T>()?);
+    decode_lanes(f, i, b, config, shape)
+}
+
+/// Decodes a struct of shape `shape` from borrowed lanes, all of whose values must be read.
+pub(crate) fn decode_lanes<'de, T>(
+    f: &'de [f64],
+    i: &'de [i64],
+    b: &'de [bool],
+    config: Config,
+    shape: Arc<Shape>,
+) -> Result<T>
+where
+    T: Deserialize<'de>,
+{
+    let mut deserializer = Deserializer::from_slices(f, i, b)
+        .with_config(config)
+        .with_shape(shape);
+    let res = T::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(res)
 }
 
 #[cfg(test)]
@@ -65,7 +512,15 @@ where
 mod tests {
     use serde::{Deserialize, Serialize};
 
-    use super::{decode, encode};
+    use super::{
+        attrs::OneHot,
+        config::{Config, VariantRepr, WideIntPolicy},
+        decode, decode_as_with_config, decode_padded, decode_padded_with_config,
+        decode_with_config, encode, encode_as, encode_as_with_config, encode_padded,
+        encode_padded_with_config, encode_with_config,
+        error::Error,
+        Encoder, EncodingOf, Lane,
+    };
 
     /// Testing struct -> encoding -> struct -> encoding
     #[test]
@@ -102,4 +557,502 @@ mod tests {
         assert_eq!(foo, foo_decoded);
         assert_eq!(encoding, foo_decoded_encoding);
     }
+
+    /// Testing that every wide int policy reverses itself
+    #[test]
+    fn wide_ints() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Ids {
+            a: u64,
+            b: i128,
+            c: u128,
+        }
+
+        let small = Ids { a: 1, b: -2, c: 3 };
+        let large = Ids {
+            a: u64::MAX,
+            b: i128::MIN,
+            c: u128::MAX,
+        };
+        let unsigned = Ids {
+            a: u64::MAX,
+            b: -2,
+            c: u64::MAX.into(),
+        };
+
+        let policies = [
+            (WideIntPolicy::Reject, vec![&small]),
+            (WideIntPolicy::Reinterpret, vec![&small, &unsigned]),
+            (WideIntPolicy::Split, vec![&small, &unsigned, &large]),
+        ];
+
+        for (wide_ints, values) in policies {
+            let config = Config {
+                wide_ints,
+                ..Default::default()
+            };
+            for value in values {
+                let encoding = encode_with_config(value, config).unwrap();
+                let decoded: Ids = decode_with_config(&encoding, config).unwrap();
+                assert_eq!(value, &decoded);
+            }
+        }
+
+        let config = Config {
+            wide_ints: WideIntPolicy::Reinterpret,
+            ..Default::default()
+        };
+        assert!(matches!(
+            encode_with_config(&large, config),
+            Err(Error::IntOutOfRange { ty: "i64", .. })
+        ));
+        assert!(matches!(
+            encode(&unsigned),
+            Err(Error::IntOutOfRange { ty: "i64", .. })
+        ));
+    }
+
+    /// Testing that options round-trip and keep the same width either way
+    #[test]
+    fn options() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Bar {
+            a: f64,
+            b: Option<i64>,
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Foo {
+            a: Option<Bar>,
+            b: Option<bool>,
+            c: i64,
+        }
+
+        let values = [
+            Foo {
+                a: Some(Bar { a: 1.0, b: Some(2) }),
+                b: Some(false),
+                c: 3,
+            },
+            Foo {
+                a: Some(Bar { a: 1.0, b: None }),
+                b: None,
+                c: 3,
+            },
+            Foo {
+                a: None,
+                b: Some(true),
+                c: 3,
+            },
+        ];
+
+        for foo in values {
+            let encoding = encode_padded(&foo).unwrap();
+            assert_eq!(encoding.f.len(), 1);
+            assert_eq!(encoding.i.len(), 2);
+            assert_eq!(encoding.b.len(), 4);
+
+            let foo_decoded: Foo = decode_padded(&encoding).unwrap();
+            assert_eq!(foo, foo_decoded);
+        }
+    }
+
+    /// Testing that types rejecting zero, which the shape is traced with, can be encoded
+    #[test]
+    fn nonzero() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Foo {
+            a: std::num::NonZeroU32,
+            b: Option<std::num::NonZeroI64>,
+        }
+
+        let foo = Foo {
+            a: 5.try_into().unwrap(),
+            b: None,
+        };
+        let encoding = encode_padded(&foo).unwrap();
+        assert_eq!(encoding.i, vec![5, 0]);
+        assert_eq!(decode_padded::<Foo>(&encoding).unwrap(), foo);
+    }
+
+    /// Testing that an encoder refills the same buffers
+    #[test]
+    fn encoder() {
+        let config = Config {
+            max_seq_len: Some(2),
+            ..Default::default()
+        };
+        let value = |n: i64| (vec![n; (n % 3) as usize], (n % 2 == 0).then_some(n as f64));
+        let mut encoder = Encoder::with_config(config).unwrap();
+        let i = encoder.encoding().i.as_ptr();
+        for n in 0..10 {
+            let encoding = encoder.encode(&value(n)).unwrap();
+            assert_eq!(
+                *encoding,
+                encode_padded_with_config(&value(n), config).unwrap()
+            );
+            assert_eq!(encoding.i.as_ptr(), i);
+        }
+        assert_eq!(
+            encoder.into_encoding(),
+            encode_padded_with_config(&value(9), config).unwrap()
+        );
+    }
+
+    /// Testing that an encoder recovers from an error inside of an overridden field
+    #[test]
+    fn encoder_after_error() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        enum Color {
+            Red,
+            Green,
+        }
+
+        let mut encoder = Encoder::<(OneHot<(u64, Color)>, Color)>::new().unwrap();
+        assert!(matches!(
+            encoder.encode(&(OneHot((u64::MAX, Color::Red)), Color::Red)),
+            Err(Error::IntOutOfRange { ty: "i64", .. })
+        ));
+
+        // the one-hot override ended along with the failed value
+        let value = (OneHot((1, Color::Green)), Color::Green);
+        let encoding = encoder.encode(&value).unwrap();
+        assert_eq!(encoding.b, vec![false, true]);
+        assert_eq!(encoding.i, vec![1, 1]);
+        assert_eq!(*encoding, encode_padded(&value).unwrap());
+    }
+
+    /// Testing that sequences padded to a max length round-trip with the same width
+    #[test]
+    fn max_seq_len() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Foo {
+            a: Vec<f64>,
+            b: Option<Vec<i64>>,
+        }
+
+        let config = Config {
+            max_seq_len: Some(3),
+            ..Default::default()
+        };
+
+        let values = [
+            Foo { a: vec![], b: None },
+            Foo {
+                a: vec![1.0, 2.0],
+                b: Some(vec![3]),
+            },
+            Foo {
+                a: vec![1.0, 2.0, 3.0],
+                b: Some(vec![]),
+            },
+        ];
+
+        for foo in values {
+            let encoding = encode_padded_with_config(&foo, config).unwrap();
+            assert_eq!(encoding.f.len(), 3);
+            assert_eq!(encoding.i.len(), 5);
+            assert_eq!(encoding.b.len(), 1);
+
+            let foo_decoded: Foo = decode_padded_with_config(&encoding, config).unwrap();
+            assert_eq!(foo, foo_decoded);
+        }
+
+        let foo = Foo {
+            a: vec![1.0, 2.0, 3.0, 4.0],
+            b: None,
+        };
+        let encoding = encode_padded_with_config(&foo, config).unwrap();
+        let foo_decoded: Foo = decode_padded_with_config(&encoding, config).unwrap();
+        assert_eq!(foo_decoded.a, vec![1.0, 2.0, 3.0]);
+    }
+
+    /// Testing that unit variants round-trip with every variant representation
+    #[test]
+    fn unit_variants() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        enum Weather {
+            Sunny,
+            Cloudy,
+            Rainy,
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Foo {
+            a: Weather,
+            b: Option<Weather>,
+            c: Vec<Weather>,
+        }
+
+        let foo = Foo {
+            a: Weather::Rainy,
+            b: Some(Weather::Cloudy),
+            c: vec![Weather::Sunny, Weather::Rainy],
+        };
+
+        for variants in [
+            VariantRepr::Index,
+            VariantRepr::OneHot,
+            VariantRepr::OneHotFloat,
+        ] {
+            let config = Config {
+                variants,
+                ..Default::default()
+            };
+            let encoding = encode_padded_with_config(&foo, config).unwrap();
+            let foo_decoded: Foo = decode_padded_with_config(&encoding, config).unwrap();
+            assert_eq!(foo, foo_decoded);
+        }
+    }
+
+    /// Testing that data-carrying variants round-trip and keep the same width
+    #[test]
+    fn data_variants() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        enum Target {
+            Position(f64, f64),
+            Entity { id: u32, hostile: bool },
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        enum Action {
+            Move(Target),
+            Jump(i64),
+            Idle,
+        }
+
+        let actions = [
+            Action::Move(Target::Position(1.0, 2.0)),
+            Action::Move(Target::Entity {
+                id: 3,
+                hostile: true,
+            }),
+            Action::Jump(4),
+            Action::Idle,
+        ];
+
+        for variants in [
+            VariantRepr::Index,
+            VariantRepr::OneHot,
+            VariantRepr::OneHotFloat,
+        ] {
+            let config = Config {
+                variants,
+                ..Default::default()
+            };
+            let widths = actions
+                .iter()
+                .map(|action| {
+                    let encoding = encode_padded_with_config(action, config).unwrap();
+                    let action_decoded: Action =
+                        decode_padded_with_config(&encoding, config).unwrap();
+                    assert_eq!(action, &action_decoded);
+                    (encoding.f.len(), encoding.i.len(), encoding.b.len())
+                })
+                .collect::<Vec<_>>();
+            assert!(widths.iter().all(|width| *width == widths[0]));
+        }
+    }
+
+    /// Testing that newtype, tuple and unit structs round-trip
+    #[test]
+    fn wrapper_structs() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Meters(f64);
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Velocity(Meters, Meters);
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Grounded;
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        enum State {
+            Flying(Velocity),
+            Landed(Grounded),
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Foo {
+            altitude: Option<Meters>,
+            state: State,
+            marker: Grounded,
+        }
+
+        let foo = Foo {
+            altitude: Some(Meters(1.0)),
+            state: State::Flying(Velocity(Meters(2.0), Meters(3.0))),
+            marker: Grounded,
+        };
+
+        let encoding = encode(&foo).unwrap();
+        let foo_decoded: Foo = decode(&encoding).unwrap();
+        assert_eq!(foo, foo_decoded);
+
+        let foo = Foo {
+            altitude: None,
+            state: State::Landed(Grounded),
+            marker: Grounded,
+        };
+
+        let encoding = encode(&foo).unwrap();
+        let foo_decoded: Foo = decode(&encoding).unwrap();
+        assert_eq!(foo, foo_decoded);
+    }
+
+    /// Testing that unsupported types are reported along with where they occur
+    #[test]
+    fn unsupported() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Bar {
+            c: bool,
+            d: (f64, String),
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Foo {
+            a: f64,
+            bar: Bar,
+        }
+
+        let foo = Foo {
+            a: 1.0,
+            bar: Bar {
+                c: true,
+                d: (2.0, "three".to_string()),
+            },
+        };
+
+        let err = encode_padded(&foo).unwrap_err();
+        assert!(matches!(
+            &err,
+            Error::Unsupported { kind: "string", path } if path == "bar.d.1"
+        ));
+        assert_eq!(err.to_string(), "string is not supported at `bar.d.1`");
+
+        let encoding = super::Encoding::default();
+        assert!(matches!(
+            decode_padded::<Foo>(&encoding),
+            Err(Error::Unsupported { kind: "string", path }) if path == "bar.d.1"
+        ));
+
+        // without a shape, the serializer reports the value it was given
+        assert!(matches!(
+            encode(&foo),
+            Err(Error::Unsupported { kind: "str", path }) if path == "bar.d.1"
+        ));
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        enum Baz {
+            Named {
+                map: std::collections::HashMap<i64, f64>,
+            },
+        }
+
+        assert!(matches!(
+            encode(&Baz::Named { map: Default::default() }),
+            Err(Error::Unsupported { kind: "map", path }) if path == "Named.map"
+        ));
+        assert!(matches!(
+            encode(&'c'),
+            Err(Error::Unsupported { kind: "char", path }) if path.is_empty()
+        ));
+    }
+
+    /// Testing that values are converted to other element types as they are written
+    #[test]
+    fn element_types() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Foo {
+            a: f64,
+            b: Vec<Option<u16>>,
+            c: bool,
+        }
+
+        let foo = Foo {
+            a: 0.25,
+            b: vec![Some(300), None],
+            c: true,
+        };
+        let config = Config {
+            max_seq_len: Some(3),
+            ..Default::default()
+        };
+        let encoding: EncodingOf<f32, i32, u8> = encode_as_with_config(&foo, config).unwrap();
+        assert_eq!(encoding.f, vec![0.25]);
+        assert_eq!(encoding.i, vec![2, 300]);
+        assert_eq!(encoding.b, vec![1, 0, 1]);
+        assert_eq!(
+            encoding,
+            encode_with_config(&foo, config).unwrap().convert().unwrap()
+        );
+        assert_eq!(
+            decode_as_with_config::<Foo, _, _, _>(&encoding, config).unwrap(),
+            foo
+        );
+
+        // values that don't fit are rejected where they are written
+        assert!(matches!(
+            encode_as::<f32, u8, bool>(&foo),
+            Err(Error::ElementOutOfRange { lane: Lane::I, index: 1, ty: "u8", path }) if path == "b.0"
+        ));
+        assert!(matches!(
+            encode_as::<f32, i64, bool>(&(1e300, 1)),
+            Err(Error::ElementOutOfRange { lane: Lane::F, index: 0, ty: "f32", path }) if path == "0"
+        ));
+
+        // and so are elements that don't stand for a native value
+        let mut encoding = encoding;
+        encoding.b[2] = 2;
+        assert!(matches!(
+            encoding.to_native(),
+            Err(Error::ElementOutOfRange {
+                lane: Lane::B,
+                index: 2,
+                ty: "bool",
+                ..
+            })
+        ));
+    }
+
+    /// Testing that every value can be stored in the float lane
+    #[test]
+    fn all_floats() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Foo {
+            a: f64,
+            b: Option<u8>,
+            c: Vec<bool>,
+        }
+
+        let foo = Foo {
+            a: 0.5,
+            b: Some(3),
+            c: vec![true, false],
+        };
+        let config = Config {
+            all_floats: true,
+            max_seq_len: Some(3),
+            ..Default::default()
+        };
+        let encoding: EncodingOf<f32, i64, bool> = encode_as_with_config(&foo, config).unwrap();
+        assert_eq!(encoding.f, vec![0.5, 1.0, 3.0, 2.0, 1.0, 0.0]);
+        assert!(encoding.i.is_empty() && encoding.b.is_empty());
+        assert_eq!(
+            decode_as_with_config::<Foo, _, _, _>(&encoding, config).unwrap(),
+            foo
+        );
+
+        // model outputs are rounded and thresholded
+        let mut encoding = encode_with_config(&foo, config).unwrap();
+        encoding.f = vec![0.5, 0.7, 2.6, 1.8, 0.9, 0.2];
+        assert_eq!(
+            decode_with_config::<Foo>(&encoding, config).unwrap(),
+            Foo {
+                a: 0.5,
+                b: Some(3),
+                c: vec![true, false],
+            }
+        );
+    }
 }
@@ -0,0 +1,465 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    config::{Config, VariantRepr},
+    encode_padded_with_config,
+    error::{Error, Result},
+    layout::{Layout, Leaf, Role, VariantOf},
+    stream::Value,
+    Encoding, Lane,
+};
+
+/// Remaps an encoding laid out as `old` into an encoding of `T`, so that data encoded before a
+/// type changed can still be decoded.
+///
+/// Values are moved to the leaf of `T` with the same name, which is the dotted path of their
+/// field (see [`FeatureNames`](crate::names::FeatureNames)). Ints and bools moved to a float
+/// field, and bools moved to an int field, are converted. Leaves of `T` without a matching
+/// value are filled from `T::default()`, and values without a matching leaf are dropped. The
+/// padding written in place of an absent option isn't a value, so it's never moved.
+///
+/// The discriminants of enums are moved like any other value, so both encodings must use the
+/// same [`VariantRepr`]. With [`VariantRepr::Index`], variants are
+/// matched by their index and may only be appended; with one-hot variants, they are matched by
+/// name. Fields added to a variant that `T::default()` doesn't hold are filled with zeros.
+///
+/// [`VariantRepr::Index`]: crate::config::VariantRepr::Index
+///
+/// Example:
+/// ```rust
+/// use encodable::{decode_padded, encode_padded, layout::Layout, migrate::migrate};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Deserialize, Serialize)]
+/// struct Old {
+///     a: f64,
+///     b: i64,
+/// }
+///
+/// #[derive(Debug, Default, Deserialize, Serialize, PartialEq)]
+/// struct New {
+///     b: i64,
+///     c: bool,
+/// }
+///
+/// let encoding = encode_padded(&Old { a: 1.0, b: 2 }).unwrap();
+/// let layout = Layout::of::<Old>().unwrap();
+/// let encoding = migrate::<New>(&encoding, &layout).unwrap();
+/// assert_eq!(decode_padded::<New>(&encoding).unwrap(), New { b: 2, c: false });
+/// ```
+pub fn migrate<'de, T>(encoding: &Encoding, old: &Layout) -> Result<Encoding>
+where
+    T: Serialize + Deserialize<'de> + Default,
+{
+    migrate_with_config::<T>(encoding, old, Config::default())
+}
+
+/// Remaps an encoding laid out as `old` into an encoding of `T` using a custom config.
+pub fn migrate_with_config<'de, T>(
+    encoding: &Encoding,
+    old: &Layout,
+    config: Config,
+) -> Result<Encoding>
+where
+    T: Serialize + Deserialize<'de> + Default,
+{
+    for (lane, expected, len) in [
+        (Lane::F, old.f, encoding.f.len()),
+        (Lane::I, old.i, encoding.i.len()),
+        (Lane::B, old.b, encoding.b.len()),
+    ] {
+        if expected != len {
+            return Err(Error::LayoutMismatch {
+                lane,
+                expected,
+                len,
+            });
+        }
+    }
+
+    let new = Layout::with_config::<T>(config)?;
+    let defaults = encode_padded_with_config(&T::default(), config)?;
+    let defaults_active = active(&new.leaves, &defaults);
+    let sources: HashMap<String, &Leaf> = old
+        .leaves
+        .iter()
+        .zip(active(&old.leaves, encoding))
+        .filter(|(_, active)| *active)
+        .map(|(leaf, _)| (leaf.name(), leaf))
+        .collect();
+
+    let mut migrated = Encoding {
+        f: vec![0.0; new.f],
+        i: vec![0; new.i],
+        b: vec![false; new.b],
+    };
+    // the leaves are visited in order, so the discriminant of an enum is always migrated before
+    // it's used to find out which of its leaves hold a value
+    let mut migrated_active = Vec::with_capacity(new.leaves.len());
+    for (leaf, default_active) in new.leaves.iter().zip(defaults_active) {
+        let is_active = is_active(&new.leaves, &migrated_active, &migrated, leaf);
+        migrated_active.push(is_active);
+        if !is_active {
+            continue;
+        }
+
+        let value = sources
+            .get(&leaf.name())
+            .and_then(|source| Value::read(encoding, source).convert(leaf.lane))
+            .or_else(|| default_active.then(|| Value::read(&defaults, leaf)))
+            .unwrap_or(Value::zero(leaf.lane));
+        let value = match (leaf.role, value, config.max_seq_len) {
+            (Role::Len, Value::I(len), Some(max_len)) => Value::I(len.min(max_len as i64)),
+            (_, value, _) => value,
+        };
+        value.write(&mut migrated, leaf);
+    }
+
+    Ok(migrated)
+}
+
+/// Whether each of `leaves` holds a value in `encoding`.
+fn active(leaves: &[Leaf], encoding: &Encoding) -> Vec<bool> {
+    let mut active = Vec::with_capacity(leaves.len());
+    for leaf in leaves {
+        let is_active = is_active(leaves, &active, encoding, leaf);
+        active.push(is_active);
+    }
+    active
+}
+
+/// Whether `leaf` holds a value in `encoding`, given whether the leaves before it do. The
+/// padding of an absent option and of the variants an enum doesn't hold isn't a value.
+fn is_active(leaves: &[Leaf], active: &[bool], encoding: &Encoding, leaf: &Leaf) -> bool {
+    let in_variant = match leaf.variant {
+        None => true,
+        Some(variant) => active[variant.discriminant] && holds(leaves, encoding, variant),
+    };
+    let in_option = match leaf.option {
+        None => true,
+        Some(presence) => active[presence] && present(encoding, &leaves[presence]),
+    };
+    in_variant && in_option
+}
+
+/// Whether the option of the presence flag `presence` holds a value in `encoding`.
+fn present(encoding: &Encoding, presence: &Leaf) -> bool {
+    match Value::read(encoding, presence) {
+        Value::F(f) => f >= 0.5,
+        Value::I(i) => i != 0,
+        Value::B(b) => b,
+    }
+}
+
+/// Whether the enum of `variant` holds it in `encoding`.
+fn holds(leaves: &[Leaf], encoding: &Encoding, variant: VariantOf) -> bool {
+    let discriminant = &leaves[variant.discriminant];
+    if variant.repr == VariantRepr::Index {
+        // an index moved into another lane is read back the way the deserializer reads it
+        return match Value::read(encoding, discriminant) {
+            Value::F(f) => f.round() == f64::from(variant.index),
+            Value::I(i) => i == i64::from(variant.index),
+            Value::B(b) => u32::from(b) == variant.index,
+        };
+    }
+
+    let offset = discriminant.offset;
+    let index = variant.index as usize;
+    match discriminant.lane {
+        Lane::I => encoding.i[offset + index] != 0,
+        Lane::B => encoding.b[offset + index],
+        Lane::F => {
+            // one float per variant, the largest of which is the variant held
+            let variants = leaves[variant.discriminant..]
+                .iter()
+                .take_while(|leaf| {
+                    leaf.role == Role::Discriminant && leaf.variant == discriminant.variant
+                })
+                .count();
+            let floats = &encoding.f[offset..offset + variants];
+            floats.iter().all(|f| *f <= floats[index])
+        }
+    }
+}
+
+impl Value {
+    fn zero(lane: Lane) -> Self {
+        match lane {
+            Lane::F => Self::F(0.0),
+            Lane::I => Self::I(0),
+            Lane::B => Self::B(false),
+        }
+    }
+
+    fn read(encoding: &Encoding, leaf: &Leaf) -> Self {
+        match leaf.lane {
+            Lane::F => Self::F(encoding.f[leaf.offset]),
+            Lane::I => Self::I(encoding.i[leaf.offset]),
+            Lane::B => Self::B(encoding.b[leaf.offset]),
+        }
+    }
+
+    fn write(self, encoding: &mut Encoding, leaf: &Leaf) {
+        match self {
+            Self::F(f) => encoding.f[leaf.offset] = f,
+            Self::I(i) => encoding.i[leaf.offset] = i,
+            Self::B(b) => encoding.b[leaf.offset] = b,
+        }
+    }
+
+    /// Converts the value to one stored in `lane`, if that doesn't lose information.
+    fn convert(self, lane: Lane) -> Option<Self> {
+        match (self, lane) {
+            (Self::F(_), Lane::F) | (Self::I(_), Lane::I) | (Self::B(_), Lane::B) => Some(self),
+            (Self::I(i), Lane::F) => Some(Self::F(i as f64)),
+            (Self::B(b), Lane::F) => Some(Self::F(if b { 1.0 } else { 0.0 })),
+            (Self::B(b), Lane::I) => Some(Self::I(b.into())),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::{migrate, migrate_with_config};
+    use crate::{
+        config::{Config, VariantRepr},
+        decode_padded, decode_padded_with_config, encode_padded, encode_padded_with_config,
+        error::Error,
+        layout::Layout,
+        Lane,
+    };
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    enum OldShape {
+        Circle(f64),
+        Rect { w: f64, h: f64 },
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Old {
+        a: f64,
+        b: i32,
+        removed: bool,
+        shape: OldShape,
+        tags: Vec<i64>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    enum NewShape {
+        Circle(f64),
+        Rect { d: f64, w: f64, h: f64 },
+        Square(f64),
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct New {
+        shape: NewShape,
+        added: Option<u8>,
+        a: f64,
+        b: f64,
+        tags: Vec<i64>,
+    }
+
+    impl Default for New {
+        fn default() -> Self {
+            Self {
+                shape: NewShape::Circle(1.0),
+                added: Some(7),
+                a: 0.0,
+                b: 0.0,
+                tags: vec![],
+            }
+        }
+    }
+
+    fn old() -> Old {
+        Old {
+            a: 1.0,
+            b: 2,
+            removed: true,
+            shape: OldShape::Rect { w: 3.0, h: 4.0 },
+            tags: vec![5, 6],
+        }
+    }
+
+    #[test]
+    fn migrate_fields() {
+        let config = Config {
+            max_seq_len: Some(3),
+            variants: VariantRepr::OneHot,
+            ..Default::default()
+        };
+        let encoding = encode_padded_with_config(&old(), config).unwrap();
+        let layout = Layout::with_config::<Old>(config).unwrap();
+
+        // the new encoding doesn't have to use the same config
+        let new_config = Config {
+            max_seq_len: Some(1),
+            ..config
+        };
+        let migrated = migrate_with_config::<New>(&encoding, &layout, new_config).unwrap();
+        let new: New = decode_padded_with_config(&migrated, new_config).unwrap();
+        assert_eq!(
+            new,
+            New {
+                shape: NewShape::Rect {
+                    d: 0.0,
+                    w: 3.0,
+                    h: 4.0
+                },
+                added: Some(7),
+                a: 1.0,
+                b: 2.0,
+                tags: vec![5],
+            }
+        );
+
+        // the payload of the old variant is moved to where the new one expects it
+        let old = Old {
+            shape: OldShape::Circle(8.0),
+            ..old()
+        };
+        let encoding = encode_padded_with_config(&old, config).unwrap();
+        let migrated = migrate_with_config::<New>(&encoding, &layout, new_config).unwrap();
+        let new: New = decode_padded_with_config(&migrated, new_config).unwrap();
+        assert_eq!(new.shape, NewShape::Circle(8.0));
+    }
+
+    #[test]
+    fn absent_options() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Old {
+            a: Option<i64>,
+            b: Option<i64>,
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct New {
+            a: i64,
+            b: i64,
+        }
+
+        impl Default for New {
+            fn default() -> Self {
+                Self { a: 42, b: 43 }
+            }
+        }
+
+        // the padding of an absent option isn't moved, the field is filled from the default
+        let encoding = encode_padded(&Old {
+            a: None,
+            b: Some(2),
+        })
+        .unwrap();
+        let layout = Layout::of::<Old>().unwrap();
+        let migrated = migrate::<New>(&encoding, &layout).unwrap();
+        assert_eq!(
+            decode_padded::<New>(&migrated).unwrap(),
+            New { a: 42, b: 2 }
+        );
+    }
+
+    #[test]
+    fn index_in_floats() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        enum Sh {
+            A(i64),
+            B(f64),
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Old {
+            s: Sh,
+            x: i64,
+        }
+
+        #[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+        struct New {
+            x: i64,
+            y: bool,
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Moved {
+            x: i64,
+            s: Sh,
+        }
+
+        impl Default for Moved {
+            fn default() -> Self {
+                Self { x: 0, s: Sh::A(9) }
+            }
+        }
+
+        // the variant's index is stored as a float rather than as a one-hot block
+        let config = Config {
+            all_floats: true,
+            ..Default::default()
+        };
+        let old = Old {
+            s: Sh::B(2.0),
+            x: 3,
+        };
+        let encoding = encode_padded_with_config(&old, config).unwrap();
+        let layout = Layout::with_config::<Old>(config).unwrap();
+
+        let migrated = migrate_with_config::<New>(&encoding, &layout, config).unwrap();
+        assert_eq!(
+            decode_padded_with_config::<New>(&migrated, config).unwrap(),
+            New { x: 3, y: false }
+        );
+
+        let migrated = migrate_with_config::<Moved>(&encoding, &layout, config).unwrap();
+        assert_eq!(
+            decode_padded_with_config::<Moved>(&migrated, config).unwrap(),
+            Moved {
+                x: 3,
+                s: Sh::B(2.0)
+            }
+        );
+    }
+
+    #[test]
+    fn layout_mismatch() {
+        let config = Config {
+            max_seq_len: Some(3),
+            ..Default::default()
+        };
+        let layout = Layout::with_config::<Old>(config).unwrap();
+        let encoding = encode_padded(&(1.0, 2)).unwrap();
+        assert!(matches!(
+            migrate::<New>(&encoding, &layout),
+            Err(Error::LayoutMismatch {
+                lane: Lane::F,
+                expected: 3,
+                len: 1,
+            })
+        ));
+
+        let encoding = encode_padded_with_config(&old(), config).unwrap();
+        assert!(matches!(
+            migrate::<New>(&encoding, &layout),
+            Err(Error::UnboundedSeq { .. })
+        ));
+
+        #[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+        struct Unchanged {
+            a: f64,
+            b: i32,
+        }
+
+        let encoding = encode_padded(&Unchanged { a: 1.0, b: 2 }).unwrap();
+        let layout = Layout::of::<Unchanged>().unwrap();
+        let migrated = migrate::<Unchanged>(&encoding, &layout).unwrap();
+        assert_eq!(migrated, encoding);
+        assert_eq!(
+            decode_padded::<Unchanged>(&migrated).unwrap(),
+            Unchanged { a: 1.0, b: 2 }
+        );
+    }
+}
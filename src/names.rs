@@ -0,0 +1,216 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    config::Config, error::Result, layout::Layout, serializer::Serializer, shape::Shape, Lane,
+};
+
+/// The names of the values of an [`Encoding`](crate::Encoding), lined up index by index with
+/// its lanes.
+///
+/// Values are named after the dotted path of their field, such as `bar.d.0`. The values that
+/// describe a field rather than hold it are named with a suffix: `#some` for the presence flag
+/// of an option, `#len` for the length of a sequence, `#variant` for the discriminant of an enum,
+/// and `#hi`/`#lo` for the halves of a split wide int.
+///
+/// Example:
+/// ```rust
+/// use encodable::names::FeatureNames;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Deserialize, Serialize)]
+/// struct Foo {
+///     a: f64,
+///     b: Option<(i64, i64)>,
+/// }
+///
+/// let names = FeatureNames::of::<Foo>().unwrap();
+/// assert_eq!(names.f, vec!["a"]);
+/// assert_eq!(names.i, vec!["b.0", "b.1"]);
+/// assert_eq!(names.b, vec!["b#some"]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeatureNames {
+    pub f: Vec<String>,
+    pub i: Vec<String>,
+    pub b: Vec<String>,
+}
+
+impl FeatureNames {
+    /// The names of the values of any `T`. The values shared by the variants of an enum are named
+    /// after every variant that uses them, separated by `|`.
+    pub fn of<'de, T>() -> Result<Self>
+    where
+        T: Deserialize<'de>,
+    {
+        Self::with_config::<T>(Config::default())
+    }
+
+    /// The names of the values of any `T` when encoded using a custom config.
+    pub fn with_config<'de, T>(config: Config) -> Result<Self>
+    where
+        T: Deserialize<'de>,
+    {
+        Ok(Self::from_layout(&Layout::with_config::<T>(config)?))
+    }
+
+    /// The names of the values of `value`. Unlike [`FeatureNames::of`], the values shared by the
+    /// variants of an enum are only named after the variant `value` holds, and the rest of the
+    /// shared region is named with a `#pad` suffix.
+    pub fn of_value<'de, T>(value: &T) -> Result<Self>
+    where
+        T: Serialize + Deserialize<'de>,
+    {
+        Self::of_value_with_config(value, Config::default())
+    }
+
+    /// The names of the values of `value` when encoded using a custom config.
+    pub fn of_value_with_config<'de, T>(value: &T, config: Config) -> Result<Self>
+    where
+        T: Serialize + Deserialize<'de>,
+    {
+        let shape = Shape::trace::<T>()?;
+        let mut serializer = Serializer::default()
+            .with_config(config)
+            .with_shape(shape.into())
+            .with_names();
+        value.serialize(&mut serializer)?;
+        let (_, names) = serializer.consume_with_names();
+        Ok(names.unwrap_or_default())
+    }
+
+    /// Names the values of a layout. Values shared by several leaves get all of their names.
+    pub(crate) fn from_layout(layout: &Layout) -> Self {
+        let mut names = Self {
+            f: vec![String::new(); layout.f],
+            i: vec![String::new(); layout.i],
+            b: vec![String::new(); layout.b],
+        };
+        for leaf in &layout.leaves {
+            let name = &mut names.lane_mut(leaf.lane)[leaf.offset];
+            if !name.is_empty() {
+                name.push('|');
+            }
+            name.push_str(&leaf.name());
+        }
+        names
+    }
+
+    /// Prefixes every name with the path of the value they belong to.
+    pub(crate) fn prefixed(mut self, prefix: &str) -> Self {
+        for name in self.f.iter_mut().chain(&mut self.i).chain(&mut self.b) {
+            *name = name
+                .split('|')
+                .map(|name| join(prefix, name))
+                .collect::<Vec<_>>()
+                .join("|");
+        }
+        self
+    }
+
+    /// Appends the names of `other` to the end of each lane.
+    pub(crate) fn extend(&mut self, other: Self) {
+        self.f.extend(other.f);
+        self.i.extend(other.i);
+        self.b.extend(other.b);
+    }
+
+    pub(crate) fn lane_mut(&mut self, lane: Lane) -> &mut Vec<String> {
+        match lane {
+            Lane::F => &mut self.f,
+            Lane::I => &mut self.i,
+            Lane::B => &mut self.b,
+        }
+    }
+}
+
+/// Joins the path of a value with a name relative to it.
+pub(crate) fn join(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() || name.is_empty() || name.starts_with('#') {
+        format!("{prefix}{name}")
+    } else {
+        format!("{prefix}.{name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::FeatureNames;
+    use crate::{
+        config::{Config, VariantRepr, WideIntPolicy},
+        encode_padded_with_config,
+    };
+
+    #[derive(Debug, Serialize, Deserialize)]
+    enum Shape {
+        Circle(f64),
+        Rect { w: f64, h: f64 },
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Bar {
+        c: bool,
+        d: (f64, u64),
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Foo {
+        a: Option<Shape>,
+        bar: Bar,
+        e: Vec<i64>,
+    }
+
+    #[test]
+    fn of_type() {
+        let config = Config {
+            wide_ints: WideIntPolicy::Split,
+            max_seq_len: Some(2),
+            variants: VariantRepr::OneHot,
+            ..Default::default()
+        };
+        let names = FeatureNames::with_config::<Foo>(config).unwrap();
+
+        assert_eq!(names.f, vec!["a.Circle|a.Rect.w", "a.Rect.h", "bar.d.0"]);
+        assert_eq!(
+            names.i,
+            vec!["bar.d.1#hi", "bar.d.1#lo", "e#len", "e.0", "e.1"]
+        );
+        assert_eq!(
+            names.b,
+            vec!["a#some", "a.Circle#variant", "a.Rect#variant", "bar.c"]
+        );
+    }
+
+    #[test]
+    fn of_value() {
+        let config = Config {
+            max_seq_len: Some(2),
+            ..Default::default()
+        };
+        let foo = Foo {
+            a: Some(Shape::Circle(1.0)),
+            bar: Bar {
+                c: true,
+                d: (2.0, 3),
+            },
+            e: vec![4],
+        };
+        let names = FeatureNames::of_value_with_config(&foo, config).unwrap();
+
+        assert_eq!(names.f, vec!["a.Circle", "a#pad", "bar.d.0"]);
+        assert_eq!(names.i, vec!["a#variant", "bar.d.1", "e#len", "e.0", "e.1"]);
+        assert_eq!(names.b, vec!["a#some", "bar.c"]);
+
+        let encoding = encode_padded_with_config(&foo, config).unwrap();
+        assert_eq!(names.f.len(), encoding.f.len());
+        assert_eq!(names.i.len(), encoding.i.len());
+        assert_eq!(names.b.len(), encoding.b.len());
+
+        // absent values are named after what they stand in for
+        let foo = Foo { a: None, ..foo };
+        let names = FeatureNames::of_value_with_config(&foo, config).unwrap();
+        assert_eq!(names.f, vec!["a.Circle|a.Rect.w", "a.Rect.h", "bar.d.0"]);
+        assert_eq!(names.i, vec!["a#variant", "bar.d.1", "e#len", "e.0", "e.1"]);
+    }
+}
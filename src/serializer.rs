@@ -1,20 +1,653 @@
-use serde::Serialize;
+use std::sync::Arc;
 
+use serde::{Deserialize, Serialize};
+
+use super::attrs::Override;
+use super::config::{Config, VariantRepr, WideIntPolicy};
+use super::element::Element;
 use super::error::Error;
-use super::Encoding;
+use super::layout::Layout;
+use super::names::FeatureNames;
+use super::shape::{display_path, Segment, Shape, Width};
+use super::{Encoding, EncodingOf, Lane};
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl<F, I, B> Sealed for crate::EncodingOf<F, I, B> {}
+    impl<F, I, B> Sealed for super::Slices<'_, F, I, B> {}
+    impl Sealed for crate::stream::Stream {}
+}
+
+/// Why an [`Output`] couldn't write a value.
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejected {
+    /// The lane's slice holds `len` values, but `needed` are needed to write the value.
+    Full { len: usize, needed: usize },
+    /// The value doesn't fit in the lane's element type, which is named.
+    OutOfRange(&'static str),
+}
+
+/// Where a [`Serializer`] writes values: the growable lanes of an [`EncodingOf`], the
+/// caller-owned [`Slices`] of a fixed length, or the single
+/// [`Stream`](crate::stream::Stream) of tagged values.
+///
+/// This trait is sealed: it is implemented by those three types only.
+pub trait Output: sealed::Sealed {
+    /// The number of values written to `lane` so far.
+    fn written(&self, lane: Lane) -> usize;
+
+    /// Appends a float to the `f` lane.
+    #[doc(hidden)]
+    fn push_f(&mut self, f: f64) -> Result<(), Rejected>;
+
+    /// Appends an int to the `i` lane.
+    #[doc(hidden)]
+    fn push_i(&mut self, i: i64) -> Result<(), Rejected>;
+
+    /// Appends a bool to the `b` lane.
+    #[doc(hidden)]
+    fn push_b(&mut self, b: bool) -> Result<(), Rejected>;
+
+    /// Appends `count` zeros to `lane`.
+    #[doc(hidden)]
+    fn pad(&mut self, lane: Lane, count: usize) -> Result<(), Rejected>;
+
+    /// Overwrites the value at `index` of `lane`, which was already written, with `len`.
+    #[doc(hidden)]
+    fn set_len(&mut self, lane: Lane, index: usize, len: usize) -> Result<(), Rejected>;
+
+    /// Forgets every value that was written.
+    #[doc(hidden)]
+    fn clear(&mut self);
+
+    /// Reserves room for `width` more values.
+    #[doc(hidden)]
+    fn reserve(&mut self, _width: [usize; 3]) {}
+}
+
+/// Converts a native value into the element type `T` of a lane.
+fn element<T: Element<N>, N>(value: N) -> Result<T, Rejected> {
+    T::from_native(value).ok_or(Rejected::OutOfRange(std::any::type_name::<T>()))
+}
+
+impl<F, I, B> Output for EncodingOf<F, I, B>
+where
+    F: Element<f64>,
+    I: Element<i64>,
+    B: Element<bool>,
+{
+    fn written(&self, lane: Lane) -> usize {
+        match lane {
+            Lane::F => self.f.len(),
+            Lane::I => self.i.len(),
+            Lane::B => self.b.len(),
+        }
+    }
+
+    fn push_f(&mut self, f: f64) -> Result<(), Rejected> {
+        self.f.push(element(f)?);
+        Ok(())
+    }
+
+    fn push_i(&mut self, i: i64) -> Result<(), Rejected> {
+        self.i.push(element(i)?);
+        Ok(())
+    }
+
+    fn push_b(&mut self, b: bool) -> Result<(), Rejected> {
+        self.b.push(element(b)?);
+        Ok(())
+    }
+
+    fn pad(&mut self, lane: Lane, count: usize) -> Result<(), Rejected> {
+        match lane {
+            Lane::F => self.f.resize(self.f.len() + count, element(0.0)?),
+            Lane::I => self.i.resize(self.i.len() + count, element(0)?),
+            Lane::B => self.b.resize(self.b.len() + count, element(false)?),
+        }
+        Ok(())
+    }
+
+    fn set_len(&mut self, lane: Lane, index: usize, len: usize) -> Result<(), Rejected> {
+        match lane {
+            Lane::F => self.f[index] = element(len as f64)?,
+            Lane::I => self.i[index] = element(len as i64)?,
+            Lane::B => self.b[index] = element(len != 0)?,
+        }
+        Ok(())
+    }
 
+    fn clear(&mut self) {
+        self.f.clear();
+        self.i.clear();
+        self.b.clear();
+    }
+
+    fn reserve(&mut self, [f, i, b]: [usize; 3]) {
+        self.f.reserve(f);
+        self.i.reserve(i);
+        self.b.reserve(b);
+    }
+}
+
+/// Caller-owned slices that a [`Serializer`] fills from their start, one per lane. Their
+/// elements can be of any [`Element`] type, such as `f32`.
+///
+/// Writing more values to a lane than its slice holds fails with [`Error::SliceTooShort`]
+/// instead of allocating.
 #[derive(Debug, Default)]
-pub struct Serializer {
-    encoding: Encoding,
+pub struct Slices<'a, F = f64, I = i64, B = bool> {
+    f: &'a mut [F],
+    i: &'a mut [I],
+    b: &'a mut [B],
+    /// The number of values written to the `f`, `i` and `b` slices.
+    written: [usize; 3],
+}
+
+impl<'a, F, I, B> Slices<'a, F, I, B> {
+    pub fn new(f: &'a mut [F], i: &'a mut [I], b: &'a mut [B]) -> Self {
+        Self {
+            f,
+            i,
+            b,
+            written: [0; 3],
+        }
+    }
+
+    /// The number of values written to the `f`, `i` and `b` slices.
+    pub fn lens(&self) -> [usize; 3] {
+        self.written
+    }
+}
+
+/// Writes `value` after the values written to `slice`, if there is room for it.
+fn push_slice<T>(slice: &mut [T], written: &mut usize, value: T) -> Result<(), Rejected> {
+    let len = slice.len();
+    let slot = slice.get_mut(*written).ok_or(Rejected::Full {
+        len,
+        needed: *written + 1,
+    })?;
+    *slot = value;
+    *written += 1;
+    Ok(())
+}
+
+/// Writes `count` copies of `zero` after the values written to `slice`, if there is room for
+/// them.
+fn fill_slice<T: Copy>(
+    slice: &mut [T],
+    written: &mut usize,
+    count: usize,
+    zero: T,
+) -> Result<(), Rejected> {
+    let len = slice.len();
+    let slots = slice
+        .get_mut(*written..*written + count)
+        .ok_or(Rejected::Full {
+            len,
+            needed: *written + count,
+        })?;
+    slots.fill(zero);
+    *written += count;
+    Ok(())
+}
+
+impl<F, I, B> Output for Slices<'_, F, I, B>
+where
+    F: Element<f64>,
+    I: Element<i64>,
+    B: Element<bool>,
+{
+    fn written(&self, lane: Lane) -> usize {
+        match lane {
+            Lane::F => self.written[0],
+            Lane::I => self.written[1],
+            Lane::B => self.written[2],
+        }
+    }
+
+    fn push_f(&mut self, f: f64) -> Result<(), Rejected> {
+        push_slice(self.f, &mut self.written[0], element(f)?)
+    }
+
+    fn push_i(&mut self, i: i64) -> Result<(), Rejected> {
+        push_slice(self.i, &mut self.written[1], element(i)?)
+    }
+
+    fn push_b(&mut self, b: bool) -> Result<(), Rejected> {
+        push_slice(self.b, &mut self.written[2], element(b)?)
+    }
+
+    fn pad(&mut self, lane: Lane, count: usize) -> Result<(), Rejected> {
+        match lane {
+            Lane::F => fill_slice(self.f, &mut self.written[0], count, element(0.0)?),
+            Lane::I => fill_slice(self.i, &mut self.written[1], count, element(0)?),
+            Lane::B => fill_slice(self.b, &mut self.written[2], count, element(false)?),
+        }
+    }
+
+    fn set_len(&mut self, lane: Lane, index: usize, len: usize) -> Result<(), Rejected> {
+        match lane {
+            Lane::F => self.f[index] = element(len as f64)?,
+            Lane::I => self.i[index] = element(len as i64)?,
+            Lane::B => self.b[index] = element(len != 0)?,
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.written = [0; 3];
+    }
+}
+
+/// Serializes values into an [`Output`], which is an [`Encoding`] unless the serializer was
+/// given another one, such as [`Slices`] or an [`EncodingOf`] of other element types, with
+/// [`Serializer::with_output`].
+#[derive(Debug)]
+pub struct Serializer<O = Encoding> {
+    output: O,
+    /// The config in effect, whose variant representation can be overridden while a field is
+    /// serialized.
+    config: Config,
+    /// The variant representation the config was set with.
+    variants: VariantRepr,
+    /// The shape of the value being serialized. Without it, absent values can't be padded.
+    shape: Option<Arc<Shape>>,
+    /// The path from the root to the value currently being serialized.
+    path: Vec<Segment>,
+    /// The positions of the length prefixes of the sequences being serialized.
+    seq_prefixes: Vec<(Lane, usize)>,
+    /// The lane every value is written to, if it was overridden.
+    lane: Option<Lane>,
+    /// The names of the values written so far, if they are being recorded.
+    names: Option<FeatureNames>,
+}
+
+impl Default for Serializer {
+    fn default() -> Self {
+        Self {
+            output: Encoding::default(),
+            config: Config::default(),
+            variants: VariantRepr::default(),
+            shape: None,
+            path: Vec::new(),
+            seq_prefixes: Vec::new(),
+            lane: None,
+            names: None,
+        }
+    }
 }
 
 impl Serializer {
+    /// Writes into the buffers of `encoding`, whose values are cleared, so that their capacity
+    /// is reused.
+    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
+        self.output = encoding;
+        self.reset();
+        self
+    }
+
+    /// Records the name of every value that is written.
+    pub(crate) fn with_names(mut self) -> Self {
+        self.names = Some(FeatureNames::default());
+        self
+    }
+
     pub fn consume(self) -> Encoding {
-        self.encoding
+        self.output
+    }
+
+    /// The values written so far.
+    pub fn encoding(&self) -> &Encoding {
+        &self.output
+    }
+
+    pub(crate) fn consume_with_names(self) -> (Encoding, Option<FeatureNames>) {
+        (self.output, self.names)
+    }
+}
+
+impl<O: Output> Serializer<O> {
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = config;
+        self.variants = config.variants;
+        self.lane = config.single_lane();
+        self
+    }
+
+    pub(crate) fn with_shape(mut self, shape: Arc<Shape>) -> Self {
+        self.shape = Some(shape);
+        self
+    }
+
+    /// Prepares to serialize values of `T`: traces its shape, which is needed to pad absent
+    /// values, and reserves room for a value if `T` is always encoded with the same number of
+    /// values.
+    ///
+    /// Set the config first. Along with [`Serializer::reset`], this lets a loop encode a value of
+    /// `T` after another without allocating:
+    /// ```rust
+    /// use encodable::serializer::Serializer;
+    /// use serde::Serialize;
+    ///
+    /// let mut serializer = Serializer::default().with_shape_of::<(f64, i64)>().unwrap();
+    /// for step in 0..10 {
+    ///     serializer.reset();
+    ///     (step as f64, step).serialize(&mut serializer).unwrap();
+    ///     assert_eq!(serializer.encoding().i, vec![step]);
+    /// }
+    /// ```
+    pub fn with_shape_of<'de, T>(self) -> Result<Self, Error>
+    where
+        T: Deserialize<'de>,
+    {
+        Ok(self.prepared(Arc::new(Shape::trace::<T>()?)))
+    }
+
+    /// Sets the shape of the values to serialize, and reserves room for one of them.
+    pub(crate) fn prepared(mut self, shape: Arc<Shape>) -> Self {
+        if let Some(width) = shape.encoded_width(&self.config) {
+            let written = self.written();
+            self.reserve(Width {
+                f: width.f.saturating_sub(written.f),
+                i: width.i.saturating_sub(written.i),
+                b: width.b.saturating_sub(written.b),
+            });
+        }
+        self.with_shape(shape)
+    }
+
+    /// Writes into `output` instead, keeping the config and the shape. Values that were already
+    /// written are left behind.
+    ///
+    /// Example:
+    /// ```rust
+    /// use encodable::serializer::{Serializer, Slices};
+    /// use serde::Serialize;
+    ///
+    /// let (mut f, mut i, mut b) = ([0.0; 4], [0; 4], [false; 4]);
+    /// let mut serializer = Serializer::default()
+    ///     .with_shape_of::<Option<f64>>()
+    ///     .unwrap()
+    ///     .with_output(Slices::new(&mut f, &mut i, &mut b));
+    /// Some(2.0).serialize(&mut serializer).unwrap();
+    /// assert_eq!(serializer.output().lens(), [1, 0, 1]);
+    /// assert_eq!((f[0], b[0]), (2.0, true));
+    /// ```
+    pub fn with_output<P: Output>(self, output: P) -> Serializer<P> {
+        let mut serializer = Serializer {
+            output,
+            config: self.config,
+            variants: self.variants,
+            shape: self.shape,
+            path: self.path,
+            seq_prefixes: self.seq_prefixes,
+            lane: self.lane,
+            names: self.names,
+        };
+        serializer.reset();
+        serializer
+    }
+
+    /// Replaces the output with `output`, whose values are cleared, and returns the previous
+    /// one. This lets a loop write each value into different slices without allocating.
+    pub fn replace_output(&mut self, output: O) -> O {
+        let output = std::mem::replace(&mut self.output, output);
+        self.reset();
+        output
+    }
+
+    /// Where the values are written.
+    pub fn output(&self) -> &O {
+        &self.output
+    }
+
+    pub fn into_output(self) -> O {
+        self.output
+    }
+
+    /// Forgets every value that was written while keeping the capacity of the buffers, so that
+    /// another value can be serialized without allocating. The shape and the config are kept.
+    pub fn reset(&mut self) {
+        self.output.clear();
+        self.path.clear();
+        self.seq_prefixes.clear();
+        self.config.variants = self.variants;
+        self.lane = self.config.single_lane();
+        if let Some(names) = &mut self.names {
+            *names = FeatureNames::default();
+        }
+    }
+
+    /// Reserves room for `width` more values.
+    pub(crate) fn reserve(&mut self, width: Width) {
+        self.output.reserve([width.f, width.i, width.b]);
+    }
+
+    /// The number of values written to each lane so far.
+    pub(crate) fn written(&self) -> Width {
+        Width {
+            f: self.output.written(Lane::F),
+            i: self.output.written(Lane::I),
+            b: self.output.written(Lane::B),
+        }
+    }
+
+    /// Names the next `count` values of `lane` after the current path and `suffix`.
+    fn name(&mut self, lane: Lane, suffix: &str, count: usize) {
+        if self.names.is_none() {
+            return;
+        }
+        let lane = self.lane.unwrap_or(lane);
+        let name = format!("{}{suffix}", self.current_path());
+        if let Some(names) = &mut self.names {
+            let names = names.lane_mut(lane);
+            names.extend(std::iter::repeat_n(name, count));
+        }
+    }
+
+    /// The shape of the value currently being serialized, if known.
+    fn current_shape(&self) -> Option<&Shape> {
+        self.shape.as_deref()?.at(&self.path)
+    }
+
+    /// The dotted path from the root to the value currently being serialized.
+    fn current_path(&self) -> String {
+        display_path(&self.path)
+    }
+
+    /// An error for a kind of value the encoding can't represent, at the current path.
+    fn unsupported(&self, kind: &'static str) -> Error {
+        Error::Unsupported {
+            kind,
+            path: self.current_path(),
+        }
+    }
+
+    /// Records the name of the struct field about to be serialized.
+    fn name_field(&mut self, key: &'static str) {
+        if let Some(Segment::Field(_, name)) = self.path.last_mut() {
+            *name = key;
+        }
+    }
+
+    /// Moves from the current value to the next sibling of the last child entered.
+    fn next_sibling(&mut self) {
+        if let Some(segment) = self.path.last_mut() {
+            *segment = segment.next();
+        }
+    }
+
+    /// Writes which variant an enum holds, according to the variant representation.
+    fn write_discriminant(&mut self, variant_index: u32) -> Result<(), Error> {
+        if self.config.variants == VariantRepr::Index {
+            self.push_i(variant_index.into())?;
+            self.name(Lane::I, "#variant", 1);
+            return Ok(());
+        }
+
+        let shape = self.shape.clone();
+        let variants = match shape.as_deref().and_then(|shape| shape.at(&self.path)) {
+            Some(Shape::Enum(variants)) => variants,
+            _ => {
+                return Err(serde::ser::Error::custom(
+                    "one-hot variants require the shape of the enum, see `encode_padded`",
+                ))
+            }
+        };
+        let hot = variant_index as usize;
+        let lane = match self.config.variants {
+            VariantRepr::OneHot => Lane::B,
+            _ => Lane::F,
+        };
+        for (i, (name, _)) in variants.iter().enumerate() {
+            match lane {
+                Lane::B => self.push_b(i == hot)?,
+                _ => self.push_f(if i == hot { 1.0 } else { 0.0 })?,
+            }
+            self.path.push(Segment::Variant(i as u32, name));
+            self.name(lane, "#variant", 1);
+            self.path.pop();
+        }
+        Ok(())
+    }
+
+    /// Pads the payload of an enum's variant to the width of the enum's widest variant.
+    fn pad_variant(&mut self, variant_index: u32) -> Result<(), Error> {
+        let width = self
+            .current_shape()
+            .and_then(|shape| shape.variant_padding(variant_index, &self.config));
+        if let Some(width) = width {
+            self.pad(width)?;
+            self.name(Lane::F, "#pad", width.f);
+            self.name(Lane::I, "#pad", width.i);
+            self.name(Lane::B, "#pad", width.b);
+        }
+        Ok(())
+    }
+
+    /// Leaves the payload of a tuple or struct variant.
+    fn end_variant(&mut self) -> Result<(), Error> {
+        if let Some(Segment::Variant(variant_index, _)) = self.path.pop() {
+            self.pad_variant(variant_index)?;
+        }
+        Ok(())
+    }
+
+    /// Pads a struct field that was skipped, so that the struct keeps its width.
+    fn pad_skipped_field(&mut self) -> Result<(), Error> {
+        let shape = self.shape.clone();
+        if let Some(field) = shape.as_deref().and_then(|shape| shape.at(&self.path)) {
+            self.pad_absent(field)?;
+        }
+        self.next_sibling();
+        Ok(())
+    }
+
+    /// Writes zeros in place of an absent value of `shape` at the current path.
+    fn pad_absent(&mut self, shape: &Shape) -> Result<(), Error> {
+        let Some(width) = shape.width(&self.config) else {
+            return Ok(());
+        };
+        self.pad(width)?;
+        if self.names.is_some() {
+            let prefix = self.current_path();
+            let absent = Layout::from_shape_in_lane(shape, &self.config, self.lane)
+                .map(|layout| FeatureNames::from_layout(&layout).prefixed(&prefix));
+            if let (Some(names), Ok(absent)) = (&mut self.names, absent) {
+                names.extend(absent);
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes zeros in place of a value that is absent.
+    fn pad(&mut self, width: Width) -> Result<(), Error> {
+        let width = self.lane.map_or(width, |lane| width.in_lane(lane));
+        for (lane, count) in [(Lane::F, width.f), (Lane::I, width.i), (Lane::B, width.b)] {
+            let index = self.output.written(lane);
+            let padded = self.output.pad(lane, count);
+            self.written_to(padded, lane, index)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the high and then the low 64 bits of a 128-bit value.
+    fn push_split(&mut self, bits: u128) -> Result<(), Error> {
+        self.push_i((bits >> 64) as u64 as i64)?;
+        self.push_i(bits as u64 as i64)?;
+        self.name(Lane::I, "#hi", 1);
+        self.name(Lane::I, "#lo", 1);
+        Ok(())
+    }
+
+    /// Writes a float, converting it if the lane was overridden.
+    fn push_f(&mut self, f: f64) -> Result<(), Error> {
+        let written = match self.lane {
+            None | Some(Lane::F) => self.output.push_f(f),
+            Some(Lane::I) => self.output.push_i(f as i64),
+            Some(Lane::B) => self.output.push_b(f != 0.0),
+        };
+        let lane = self.lane.unwrap_or(Lane::F);
+        self.written_to(written, lane, self.output.written(lane))
+    }
+
+    /// Writes an int, converting it if the lane was overridden.
+    fn push_i(&mut self, i: i64) -> Result<(), Error> {
+        let written = match self.lane {
+            None | Some(Lane::I) => self.output.push_i(i),
+            Some(Lane::F) => self.output.push_f(i as f64),
+            Some(Lane::B) => self.output.push_b(i != 0),
+        };
+        let lane = self.lane.unwrap_or(Lane::I);
+        self.written_to(written, lane, self.output.written(lane))
+    }
+
+    /// Writes a bool, converting it if the lane was overridden.
+    fn push_b(&mut self, b: bool) -> Result<(), Error> {
+        let written = match self.lane {
+            None | Some(Lane::B) => self.output.push_b(b),
+            Some(Lane::F) => self.output.push_f(if b { 1.0 } else { 0.0 }),
+            Some(Lane::I) => self.output.push_i(b.into()),
+        };
+        let lane = self.lane.unwrap_or(Lane::B);
+        self.written_to(written, lane, self.output.written(lane))
+    }
+
+    /// Turns the reason why a value couldn't be written to `lane` into an error at the current
+    /// path. Values that didn't fit in the lane's element type would have been at `index`.
+    fn written_to(
+        &self,
+        written: Result<(), Rejected>,
+        lane: Lane,
+        index: usize,
+    ) -> Result<(), Error> {
+        written.map_err(|rejected| match rejected {
+            Rejected::Full { len, needed } => Error::SliceTooShort {
+                lane,
+                len,
+                needed,
+                path: self.current_path(),
+            },
+            Rejected::OutOfRange(ty) => Error::ElementOutOfRange {
+                lane,
+                index,
+                ty,
+                path: self.current_path(),
+            },
+        })
+    }
+
+    /// The lane ints are currently written to, and the index of the next one.
+    fn int_position(&self) -> (Lane, usize) {
+        let lane = self.lane.unwrap_or(Lane::I);
+        (lane, self.output.written(lane))
     }
 }
 
-impl<'a> serde::ser::Serializer for &'a mut Serializer {
+impl<O: Output> serde::ser::Serializer for &mut Serializer<O> {
     type Ok = ();
     type Error = Error;
 
@@ -28,138 +661,219 @@ impl<'a> serde::ser::Serializer for &'a mut Serializer {
 
     /* Core types */
     fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
-        self.encoding.f.push(v);
+        self.push_f(v)?;
+        self.name(Lane::F, "", 1);
         Ok(())
     }
 
     fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
-        self.encoding.i.push(v);
+        self.push_i(v)?;
+        self.name(Lane::I, "", 1);
         Ok(())
     }
 
     fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
-        self.encoding.b.push(v);
+        self.push_b(v)?;
+        self.name(Lane::B, "", 1);
         Ok(())
     }
 
     /* int */
-    fn serialize_i8(self, _v: i8) -> Result<Self::Ok, Self::Error> {
-        unimplemented!()
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(v.into())
     }
 
-    fn serialize_i16(self, _v: i16) -> Result<Self::Ok, Self::Error> {
-        unimplemented!()
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(v.into())
     }
 
-    fn serialize_i32(self, _v: i32) -> Result<Self::Ok, Self::Error> {
-        unimplemented!()
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(v.into())
     }
 
-    fn serialize_i128(self, _v: i128) -> Result<Self::Ok, Self::Error> {
-        unimplemented!()
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+        match self.config.wide_ints {
+            WideIntPolicy::Reject | WideIntPolicy::Reinterpret => {
+                let v = i64::try_from(v).map_err(|_| Error::IntOutOfRange {
+                    value: v,
+                    ty: "i64",
+                    path: self.current_path(),
+                })?;
+                self.serialize_i64(v)
+            }
+            WideIntPolicy::Split => self.push_split(v as u128),
+        }
     }
 
     /* uint */
-    fn serialize_u8(self, _v: u8) -> Result<Self::Ok, Self::Error> {
-        unimplemented!()
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(v.into())
     }
 
-    fn serialize_u16(self, _v: u16) -> Result<Self::Ok, Self::Error> {
-        unimplemented!()
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(v.into())
     }
 
-    fn serialize_u32(self, _v: u32) -> Result<Self::Ok, Self::Error> {
-        unimplemented!()
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(v.into())
     }
 
-    fn serialize_u64(self, _v: u64) -> Result<Self::Ok, Self::Error> {
-        unimplemented!()
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        match self.config.wide_ints {
+            WideIntPolicy::Reject => {
+                let v = i64::try_from(v).map_err(|_| Error::IntOutOfRange {
+                    value: v.into(),
+                    ty: "i64",
+                    path: self.current_path(),
+                })?;
+                self.serialize_i64(v)
+            }
+            WideIntPolicy::Reinterpret => self.serialize_i64(v as i64),
+            WideIntPolicy::Split => self.push_split(v.into()),
+        }
     }
 
-    fn serialize_u128(self, _v: u128) -> Result<Self::Ok, Self::Error> {
-        unimplemented!()
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+        match self.config.wide_ints {
+            WideIntPolicy::Reject => {
+                let v = i64::try_from(v).map_err(|_| Error::UIntOutOfRange {
+                    value: v,
+                    ty: "i64",
+                    path: self.current_path(),
+                })?;
+                self.serialize_i64(v)
+            }
+            WideIntPolicy::Reinterpret => {
+                let v = u64::try_from(v).map_err(|_| Error::UIntOutOfRange {
+                    value: v,
+                    ty: "u64",
+                    path: self.current_path(),
+                })?;
+                self.serialize_i64(v as i64)
+            }
+            WideIntPolicy::Split => self.push_split(v),
+        }
     }
 
     /* float */
-    fn serialize_f32(self, _v: f32) -> Result<Self::Ok, Self::Error> {
-        unimplemented!()
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_f64(v.into())
     }
 
     /* strings */
     fn serialize_str(self, _v: &str) -> Result<Self::Ok, Self::Error> {
-        unimplemented!()
+        Err(self.unsupported("str"))
     }
 
     fn serialize_char(self, _v: char) -> Result<Self::Ok, Self::Error> {
-        unimplemented!()
+        Err(self.unsupported("char"))
     }
 
     /* bytes */
     fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok, Self::Error> {
-        unimplemented!()
+        Err(self.unsupported("bytes"))
     }
 
     /* misc */
     fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
-        unimplemented!()
+        self.push_b(false)?;
+        self.name(Lane::B, "#some", 1);
+        let shape = self.shape.clone();
+        if let Some(Shape::Option(inner)) = shape.as_deref().and_then(|shape| shape.at(&self.path))
+        {
+            self.pad_absent(inner)?;
+        }
+        Ok(())
     }
 
-    fn serialize_some<T: ?Sized>(self, _value: &T) -> Result<Self::Ok, Self::Error>
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        unimplemented!()
+        self.push_b(true)?;
+        self.name(Lane::B, "#some", 1);
+        self.path.push(Segment::Some);
+        value.serialize(&mut *self)?;
+        self.path.pop();
+        Ok(())
     }
 
     fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
-        unimplemented!()
+        Ok(())
     }
 
     fn serialize_unit_variant(
         self,
         _name: &'static str,
-        _variant_index: u32,
+        variant_index: u32,
         _variant: &'static str,
     ) -> Result<Self::Ok, Self::Error> {
-        unimplemented!()
+        self.write_discriminant(variant_index)?;
+        self.pad_variant(variant_index)
     }
 
     /* list / map */
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
-        unimplemented!()
+        Err(self.unsupported("map"))
     }
 
     fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
-        unimplemented!()
+        // the length is written once all of the elements have been seen
+        self.seq_prefixes.push(self.int_position());
+        self.push_i(0)?;
+        self.name(Lane::I, "#len", 1);
+        self.path.push(Segment::Index(0));
+        Ok(self)
     }
 
     /* struct */
     fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
-        unimplemented!()
+        Ok(())
     }
 
-    fn serialize_newtype_struct<T: ?Sized>(
+    fn serialize_newtype_struct<T>(
         self,
-        _name: &'static str,
-        _value: &T,
+        name: &'static str,
+        value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        unimplemented!()
+        match Override::from_name(name) {
+            None => value.serialize(self),
+            Some(Override::Variants(variants)) => {
+                let outer = self.config.variants;
+                self.config.variants = variants;
+                let result = value.serialize(&mut *self);
+                self.config.variants = outer;
+                result
+            }
+            Some(Override::Lane(lane)) => {
+                // an outer override wins over the ones inside of it
+                let outer = self.lane;
+                self.lane = outer.or(Some(lane));
+                let result = value.serialize(&mut *self);
+                self.lane = outer;
+                result
+            }
+        }
     }
 
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T>(
         self,
         _name: &'static str,
-        _variant_index: u32,
-        _variant: &'static str,
-        _value: &T,
+        variant_index: u32,
+        variant: &'static str,
+        value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        unimplemented!()
+        self.write_discriminant(variant_index)?;
+        self.path.push(Segment::Variant(variant_index, variant));
+        value.serialize(&mut *self)?;
+        self.path.pop();
+        self.pad_variant(variant_index)
     }
 
     fn serialize_struct(
@@ -167,170 +881,219 @@ impl<'a> serde::ser::Serializer for &'a mut Serializer {
         _name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStruct, Self::Error> {
+        self.path.push(Segment::Field(0, ""));
         Ok(self)
     }
 
     fn serialize_struct_variant(
         self,
         _name: &'static str,
-        _variant_index: u32,
-        _variant: &'static str,
+        variant_index: u32,
+        variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStructVariant, Self::Error> {
-        unimplemented!()
+        self.write_discriminant(variant_index)?;
+        self.path.push(Segment::Variant(variant_index, variant));
+        self.path.push(Segment::Field(0, ""));
+        Ok(self)
     }
 
     fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.path.push(Segment::Index(0));
         Ok(self)
     }
 
     fn serialize_tuple_variant(
         self,
         _name: &'static str,
-        _variant_index: u32,
-        _variant: &'static str,
+        variant_index: u32,
+        variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleVariant, Self::Error> {
-        todo!()
+        self.write_discriminant(variant_index)?;
+        self.path.push(Segment::Variant(variant_index, variant));
+        self.path.push(Segment::Index(0));
+        Ok(self)
     }
 
     fn serialize_tuple_struct(
         self,
         _name: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeTupleStruct, Self::Error> {
-        unimplemented!()
+        self.serialize_tuple(len)
     }
 }
 
-impl<'a> serde::ser::SerializeSeq for &'a mut Serializer {
+impl<O: Output> serde::ser::SerializeSeq for &mut Serializer<O> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, _value: &T) -> Result<(), Self::Error>
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        unimplemented!()
+        if let (Some(Segment::Index(i)), Some(max_len)) =
+            (self.path.last(), self.config.max_seq_len)
+        {
+            if *i >= max_len {
+                return Ok(());
+            }
+        }
+        value.serialize(&mut **self)?;
+        self.next_sibling();
+        Ok(())
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        unimplemented!()
+        let len = match self.path.pop() {
+            Some(Segment::Index(len)) => len,
+            _ => 0,
+        };
+        if let Some((lane, prefix)) = self.seq_prefixes.pop() {
+            let written = self.output.set_len(lane, prefix, len);
+            self.written_to(written, lane, prefix)?;
+        }
+        if let Some(max_len) = self.config.max_seq_len {
+            let shape = self.shape.clone();
+            if let Some(Shape::Seq(element)) =
+                shape.as_deref().and_then(|shape| shape.at(&self.path))
+            {
+                for i in len..max_len {
+                    self.path.push(Segment::Index(i));
+                    self.pad_absent(element)?;
+                    self.path.pop();
+                }
+            }
+        }
+        Ok(())
     }
 }
 
-impl<'a> serde::ser::SerializeTupleStruct for &'a mut Serializer {
+impl<O: Output> serde::ser::SerializeTupleStruct for &mut Serializer<O> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, _value: &T) -> Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        unimplemented!()
+        serde::ser::SerializeTuple::serialize_element(self, value)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        unimplemented!()
+        serde::ser::SerializeTuple::end(self)
     }
 }
 
-impl<'a> serde::ser::SerializeTupleVariant for &'a mut Serializer {
+impl<O: Output> serde::ser::SerializeTupleVariant for &mut Serializer<O> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, _value: &T) -> Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        unimplemented!()
+        value.serialize(&mut **self)?;
+        self.next_sibling();
+        Ok(())
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        unimplemented!()
+        self.path.pop();
+        self.end_variant()
     }
 }
 
-impl<'a> serde::ser::SerializeMap for &'a mut Serializer {
+impl<O: Output> serde::ser::SerializeMap for &mut Serializer<O> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_key<T: ?Sized>(&mut self, _key: &T) -> Result<(), Self::Error>
+    fn serialize_key<T>(&mut self, _key: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        unimplemented!()
+        Err(self.unsupported("map"))
     }
 
-    fn serialize_value<T: ?Sized>(&mut self, _value: &T) -> Result<(), Self::Error>
+    fn serialize_value<T>(&mut self, _value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        unimplemented!()
+        Err(self.unsupported("map"))
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        unimplemented!()
+        Err(self.unsupported("map"))
     }
 }
 
-impl<'a> serde::ser::SerializeTuple for &'a mut Serializer {
+impl<O: Output> serde::ser::SerializeTuple for &mut Serializer<O> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        value.serialize(&mut **self)
+        value.serialize(&mut **self)?;
+        self.next_sibling();
+        Ok(())
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.path.pop();
         Ok(())
     }
 }
 
-impl<'a> serde::ser::SerializeStruct for &'a mut Serializer {
+impl<O: Output> serde::ser::SerializeStruct for &mut Serializer<O> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(
-        &mut self,
-        _key: &'static str,
-        value: &T,
-    ) -> Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        value.serialize(&mut **self)
+        self.name_field(key);
+        value.serialize(&mut **self)?;
+        self.next_sibling();
+        Ok(())
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Self::Error> {
+        self.name_field(key);
+        self.pad_skipped_field()
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.path.pop();
         Ok(())
     }
 }
 
-impl<'a> serde::ser::SerializeStructVariant for &'a mut Serializer {
+impl<O: Output> serde::ser::SerializeStructVariant for &mut Serializer<O> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(
-        &mut self,
-        _key: &'static str,
-        _value: &T,
-    ) -> Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        unimplemented!()
+        self.name_field(key);
+        value.serialize(&mut **self)?;
+        self.next_sibling();
+        Ok(())
     }
 
-    fn skip_field(&mut self, _key: &'static str) -> Result<(), Self::Error> {
-        unimplemented!()
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Self::Error> {
+        self.name_field(key);
+        self.pad_skipped_field()
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        unimplemented!()
+        self.path.pop();
+        self.end_variant()
     }
 }
 
@@ -338,9 +1101,16 @@ impl<'a> serde::ser::SerializeStructVariant for &'a mut Serializer {
 mod tests {
     use approx::assert_relative_eq;
     use itertools::izip;
-    use serde::Serialize;
+    use serde::{Deserialize, Serialize};
 
-    use crate::{encode, Encoding};
+    use super::{Serializer, Slices};
+    use crate::{
+        config::{Config, VariantRepr, WideIntPolicy},
+        encode, encode_into, encode_into_with_config, encode_padded, encode_padded_with_config,
+        encode_reusing, encode_reusing_with_config, encode_with_config,
+        error::Error,
+        Encoding, Lane,
+    };
 
     fn compare_encodings(a: &Encoding, b: &Encoding) {
         assert_eq!(a.f.len(), b.f.len());
@@ -413,4 +1183,418 @@ mod tests {
 
         compare_encodings(&encoding, &encoded);
     }
+
+    #[test]
+    fn integers() {
+        #[derive(Serialize)]
+        struct Foo {
+            a: i8,
+            b: i16,
+            c: i32,
+            d: u8,
+            e: u16,
+            f: u32,
+            g: u64,
+            h: usize,
+        }
+
+        let foo = Foo {
+            a: -1,
+            b: -2,
+            c: -3,
+            d: 4,
+            e: 5,
+            f: 6,
+            g: 7,
+            h: 8,
+        };
+
+        let encoding = Encoding {
+            f: vec![],
+            i: vec![-1, -2, -3, 4, 5, 6, 7, 8],
+            b: vec![],
+        };
+
+        let encoded = encode(&foo).unwrap();
+
+        compare_encodings(&encoding, &encoded);
+    }
+
+    #[test]
+    fn u64_out_of_range() {
+        assert!(matches!(
+            encode(&u64::MAX),
+            Err(Error::IntOutOfRange { ty: "i64", .. })
+        ));
+
+        #[derive(Serialize)]
+        struct Foo {
+            a: Vec<(i64, u64)>,
+        }
+
+        let foo = Foo {
+            a: vec![(1, 2), (3, u64::MAX)],
+        };
+        assert!(matches!(
+            encode(&foo),
+            Err(Error::IntOutOfRange { path, .. }) if path == "a.1.1"
+        ));
+    }
+
+    #[test]
+    fn split_wide_ints() {
+        let config = Config {
+            wide_ints: WideIntPolicy::Split,
+            ..Default::default()
+        };
+
+        let encoding = Encoding {
+            f: vec![],
+            i: vec![0, -1, -1, -2, 1, 0],
+            b: vec![],
+        };
+
+        let encoded = encode_with_config(&(u64::MAX, -2i128, 1u128 << 64), config).unwrap();
+
+        compare_encodings(&encoding, &encoded);
+    }
+
+    #[test]
+    fn f32s() {
+        #[derive(Serialize)]
+        struct Foo {
+            a: f32,
+            b: [f32; 2],
+        }
+
+        let foo = Foo {
+            a: 0.5,
+            b: [1.5, 2.5],
+        };
+
+        let encoding = Encoding {
+            f: vec![0.5, 1.5, 2.5],
+            i: vec![],
+            b: vec![],
+        };
+
+        let encoded = encode(&foo).unwrap();
+
+        compare_encodings(&encoding, &encoded);
+    }
+
+    #[test]
+    fn options() {
+        #[derive(Serialize, Deserialize)]
+        struct Foo {
+            a: Option<(f64, i64)>,
+            b: Option<bool>,
+        }
+
+        let foo = Foo {
+            a: Some((1.0, 2)),
+            b: Some(true),
+        };
+
+        let encoding = Encoding {
+            f: vec![1.0],
+            i: vec![2],
+            b: vec![true, true, true],
+        };
+
+        let encoded = encode(&foo).unwrap();
+
+        compare_encodings(&encoding, &encoded);
+
+        let foo = Foo { a: None, b: None };
+
+        let encoding = Encoding {
+            f: vec![],
+            i: vec![],
+            b: vec![false, false],
+        };
+
+        let encoded = encode(&foo).unwrap();
+
+        compare_encodings(&encoding, &encoded);
+
+        // padded, an absent value takes as many values as a present one
+        let encoding = Encoding {
+            f: vec![0.0],
+            i: vec![0],
+            b: vec![false, false, false],
+        };
+
+        let encoded = encode_padded(&foo).unwrap();
+
+        compare_encodings(&encoding, &encoded);
+    }
+
+    #[test]
+    fn sequences() {
+        #[derive(Serialize, Deserialize)]
+        struct Foo {
+            a: Vec<f64>,
+            b: [f32; 2],
+            c: Vec<(i64, bool)>,
+        }
+
+        let foo = Foo {
+            a: vec![1.0, 2.0, 3.0],
+            b: [4.0, 5.0],
+            c: vec![(6, true)],
+        };
+
+        let encoding = Encoding {
+            f: vec![1.0, 2.0, 3.0, 4.0, 5.0],
+            i: vec![3, 1, 6],
+            b: vec![true],
+        };
+
+        let encoded = encode(&foo).unwrap();
+
+        compare_encodings(&encoding, &encoded);
+
+        let config = Config {
+            max_seq_len: Some(2),
+            ..Default::default()
+        };
+
+        let encoding = Encoding {
+            f: vec![1.0, 2.0, 4.0, 5.0],
+            i: vec![2, 1, 6, 0],
+            b: vec![true, false],
+        };
+
+        let encoded = encode_padded_with_config(&foo, config).unwrap();
+
+        compare_encodings(&encoding, &encoded);
+    }
+
+    #[test]
+    fn unit_variants() {
+        #[derive(Serialize, Deserialize)]
+        enum Color {
+            Red,
+            Green,
+            Blue,
+        }
+
+        let colors = (Color::Green, Color::Blue, Color::Red);
+
+        let encoding = Encoding {
+            f: vec![],
+            i: vec![1, 2, 0],
+            b: vec![],
+        };
+
+        compare_encodings(&encoding, &encode(&colors).unwrap());
+
+        let config = Config {
+            variants: VariantRepr::OneHot,
+            ..Default::default()
+        };
+
+        let encoding = Encoding {
+            f: vec![],
+            i: vec![],
+            b: vec![false, true, false, false, false, true, true, false, false],
+        };
+
+        compare_encodings(
+            &encoding,
+            &encode_padded_with_config(&colors, config).unwrap(),
+        );
+
+        let config = Config {
+            variants: VariantRepr::OneHotFloat,
+            ..Default::default()
+        };
+
+        let encoding = Encoding {
+            f: vec![0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0],
+            i: vec![],
+            b: vec![],
+        };
+
+        compare_encodings(
+            &encoding,
+            &encode_padded_with_config(&colors, config).unwrap(),
+        );
+    }
+
+    #[test]
+    fn data_variants() {
+        #[derive(Serialize, Deserialize)]
+        enum Action {
+            Move { dx: f64, dy: f64 },
+            Jump(i64),
+            Wait,
+        }
+
+        let actions = (
+            Action::Move { dx: 1.0, dy: 2.0 },
+            Action::Jump(3),
+            Action::Wait,
+        );
+
+        // each action is a discriminant followed by 2 floats and 1 int
+        let encoding = Encoding {
+            f: vec![1.0, 2.0, 0.0, 0.0, 0.0, 0.0],
+            i: vec![0, 0, 1, 3, 2, 0],
+            b: vec![],
+        };
+
+        compare_encodings(&encoding, &encode_padded(&actions).unwrap());
+    }
+
+    #[test]
+    fn wrapper_structs() {
+        #[derive(Serialize)]
+        struct Meters(f64);
+
+        #[derive(Serialize)]
+        struct Point(i64, i64);
+
+        #[derive(Serialize)]
+        struct Marker;
+
+        #[derive(Serialize)]
+        struct Foo {
+            a: Meters,
+            b: Point,
+            c: Marker,
+            d: (),
+        }
+
+        let foo = Foo {
+            a: Meters(1.0),
+            b: Point(2, 3),
+            c: Marker,
+            d: (),
+        };
+
+        let encoding = Encoding {
+            f: vec![1.0],
+            i: vec![2, 3],
+            b: vec![],
+        };
+
+        compare_encodings(&encoding, &encode(&foo).unwrap());
+    }
+
+    #[test]
+    fn reused_buffers() {
+        #[derive(Serialize, Deserialize)]
+        struct Foo {
+            a: Option<(f64, f64)>,
+            b: [i64; 3],
+            c: bool,
+        }
+
+        let foo = |n: i64| Foo {
+            a: (n % 2 == 0).then_some((n as f64, -n as f64)),
+            b: [n, n + 1, n + 2],
+            c: n % 3 == 0,
+        };
+
+        // the capacity for a whole value is reserved up front
+        let mut serializer = Serializer::default().with_shape_of::<Foo>().unwrap();
+        let (f, i) = (
+            serializer.encoding().f.as_ptr(),
+            serializer.encoding().i.as_ptr(),
+        );
+        assert!(serializer.encoding().f.capacity() >= 2);
+        assert!(serializer.encoding().i.capacity() >= 3);
+        for n in 0..10 {
+            serializer.reset();
+            foo(n).serialize(&mut serializer).unwrap();
+            compare_encodings(serializer.encoding(), &encode_padded(&foo(n)).unwrap());
+            assert_eq!(serializer.encoding().f.as_ptr(), f);
+            assert_eq!(serializer.encoding().i.as_ptr(), i);
+        }
+
+        let config = Config {
+            max_seq_len: Some(1),
+            ..Default::default()
+        };
+        let mut encoding = Encoding::default();
+        encode_reusing_with_config(&mut encoding, &foo(1), config).unwrap();
+        let b = encoding.b.as_ptr();
+        encode_reusing_with_config(&mut encoding, &foo(2), config).unwrap();
+        assert_eq!(encoding.b.as_ptr(), b);
+        assert_eq!(encoding, encode_with_config(&foo(2), config).unwrap());
+
+        encode_reusing(&mut encoding, &foo(3)).unwrap();
+        assert_eq!(encoding, encode(&foo(3)).unwrap());
+    }
+
+    #[test]
+    fn slices() {
+        #[derive(Serialize, Deserialize)]
+        enum Shape {
+            Circle(f64),
+            Rect { w: f64, h: f64 },
+        }
+
+        #[derive(Serialize, Deserialize)]
+        struct Foo {
+            a: Vec<(i64, bool)>,
+            b: Option<Shape>,
+            c: Shape,
+        }
+
+        let foo = Foo {
+            a: vec![(1, true)],
+            b: None,
+            c: Shape::Circle(2.0),
+        };
+        let config = Config {
+            max_seq_len: Some(2),
+            ..Default::default()
+        };
+        let expected = encode_padded_with_config(&foo, config).unwrap();
+
+        // the values after the ones written are left untouched
+        let (mut f, mut i, mut b) = ([-1.0; 8], [-1; 8], [true; 8]);
+        let lens = encode_into_with_config(&foo, &mut f, &mut i, &mut b, config).unwrap();
+        assert_eq!(lens, [expected.f.len(), expected.i.len(), expected.b.len()]);
+        assert_eq!(f[..lens[0]], expected.f);
+        assert_eq!(i[..lens[1]], expected.i);
+        assert_eq!(b[..lens[2]], expected.b);
+        assert_eq!(f[lens[0]], -1.0);
+
+        // running out of room fails instead of panicking, both when writing and when padding
+        let err = encode_into(&foo, &mut f, &mut i[..3], &mut b).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::SliceTooShort {
+                lane: Lane::I,
+                len: 3,
+                needed: 4,
+                ..
+            }
+        ));
+        let err = encode_into_with_config(&foo, &mut f[..1], &mut i, &mut b, config).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::SliceTooShort { lane: Lane::F, len: 1, needed: 2, ref path } if path == "b"
+        ));
+
+        // a serializer writes row after row of a matrix without tracing again
+        let mut matrix = [0.0; 6];
+        let mut serializer = Serializer::default()
+            .with_shape_of::<Option<(f64, f64)>>()
+            .unwrap()
+            .with_output(Slices::default());
+        let mut flags = [false; 3];
+        for (n, (row, flag)) in matrix.chunks_mut(2).zip(flags.chunks_mut(1)).enumerate() {
+            serializer.replace_output(Slices::new(row, <&mut [i64]>::default(), flag));
+            let value = (n != 1).then_some((n as f64, -(n as f64)));
+            value.serialize(&mut serializer).unwrap();
+            assert_eq!(serializer.output().lens(), [2, 0, 1]);
+        }
+        assert_eq!(matrix, [0.0, -0.0, 0.0, 0.0, 2.0, -2.0]);
+        assert_eq!(flags, [true, false, true]);
+    }
 }
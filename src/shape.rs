@@ -0,0 +1,963 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::{Add, AddAssign, Mul, Sub};
+
+use serde::de::{DeserializeSeed, EnumAccess, IntoDeserializer, SeqAccess, VariantAccess, Visitor};
+use serde::Deserialize;
+
+use super::{
+    attrs::Override,
+    config::{Config, VariantRepr, WideIntPolicy},
+    error::{Error, Result},
+    Lane,
+};
+
+/// Types nested deeper than this are assumed to be recursive.
+const MAX_DEPTH: usize = 64;
+
+/// The structure of a type as seen through the serde data model, independent of any value.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Shape {
+    Primitive(Primitive),
+    /// A value that occupies no space, such as a unit struct or the payload of a unit variant.
+    Unit,
+    Option(Box<Shape>),
+    Seq(Box<Shape>),
+    Tuple(Vec<Shape>),
+    Struct(Vec<(&'static str, Shape)>),
+    /// An enum, along with the shape of each variant's payload.
+    Enum(Vec<(&'static str, Shape)>),
+    /// A value that is encoded differently than the config says, see [`attrs`](crate::attrs).
+    /// It is transparent to paths.
+    Override(Override, Box<Shape>),
+}
+
+/// The primitive types that end up in one of the encoding's lanes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Primitive {
+    Bool,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    F32,
+    F64,
+}
+
+/// A step from a value to one of its children.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Segment {
+    /// The n-th field of a struct, along with its name.
+    Field(usize, &'static str),
+    /// The n-th element of a tuple or a sequence.
+    Index(usize),
+    /// The payload of the n-th variant of an enum, along with its name.
+    Variant(u32, &'static str),
+    /// The value inside of an option.
+    Some,
+}
+
+/// The number of values a type occupies in each of the lanes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Width {
+    pub f: usize,
+    pub i: usize,
+    pub b: usize,
+}
+
+impl Segment {
+    /// The segment leading to the next sibling.
+    /// The name of the next field isn't known yet, and is left empty.
+    pub(crate) fn next(self) -> Self {
+        match self {
+            Self::Field(i, _) => Self::Field(i + 1, ""),
+            Self::Index(i) => Self::Index(i + 1),
+            segment => segment,
+        }
+    }
+}
+
+/// The segment leading to the `i`-th element of a tuple or a sequence, or to the `i`-th field of
+/// a struct if `fields` is given.
+pub(crate) fn element_segment(i: usize, fields: Option<&'static [&'static str]>) -> Segment {
+    match fields.and_then(|fields| fields.get(i)) {
+        Some(name) => Segment::Field(i, name),
+        None => Segment::Index(i),
+    }
+}
+
+/// Formats a path as the dotted names of its fields, variants and indices, such as `bar.d.1`.
+pub(crate) fn display_path(path: &[Segment]) -> String {
+    let names: Vec<String> = path
+        .iter()
+        .filter_map(|segment| match segment {
+            Segment::Field(_, name) | Segment::Variant(_, name) => Some(name.to_string()),
+            Segment::Index(i) => Some(i.to_string()),
+            Segment::Some => None,
+        })
+        .collect();
+    names.join(".")
+}
+
+impl Shape {
+    /// Computes the shape of `T` by deserializing it from a probing deserializer.
+    ///
+    /// A single pass only sees one variant of every enum, so `T` is deserialized again for as
+    /// long as there are variants that haven't been seen. Primitives are probed with zero, or
+    /// with one for types that reject zero, such as `NonZeroU32`.
+    pub(crate) fn trace<'de, T>() -> Result<Self>
+    where
+        T: Deserialize<'de>,
+    {
+        let mut explored = HashSet::new();
+        let mut probes = HashSet::new();
+        let (mut shape, picked) = Tracer::run::<T>(HashMap::new(), &mut probes)?;
+        explored.extend(picked);
+
+        while let Some(variant) = shape.unexplored(&explored, &mut Vec::new()) {
+            // pick the variant, and every variant that leads to it
+            let choices = variant
+                .iter()
+                .enumerate()
+                .filter_map(|(i, segment)| match segment {
+                    Segment::Variant(index, _) => Some((variant[..i].to_vec(), *index)),
+                    _ => None,
+                })
+                .collect();
+            let (traced, picked) = Tracer::run::<T>(choices, &mut probes)?;
+            if !picked.contains(&variant) {
+                return Err(serde::de::Error::custom(
+                    "failed to trace every enum variant",
+                ));
+            }
+            shape.merge(traced, &explored, &picked, &mut Vec::new());
+            explored.extend(picked);
+        }
+
+        Ok(shape)
+    }
+
+    /// The shape found by following `path` from this shape.
+    pub(crate) fn at(&self, path: &[Segment]) -> Option<&Self> {
+        path.iter()
+            .try_fold(self, |shape, segment| shape.child(*segment))
+            .map(Self::without_overrides)
+    }
+
+    /// The shape inside of any overrides.
+    fn without_overrides(&self) -> &Self {
+        match self {
+            Self::Override(_, inner) => inner.without_overrides(),
+            shape => shape,
+        }
+    }
+
+    fn child(&self, segment: Segment) -> Option<&Self> {
+        match (self, segment) {
+            (Self::Override(_, inner), segment) => inner.child(segment),
+            (Self::Option(inner), Segment::Some) => Some(inner),
+            (Self::Seq(element), Segment::Index(_)) => Some(element),
+            (Self::Tuple(elements), Segment::Index(i)) => elements.get(i),
+            (Self::Struct(fields), Segment::Field(i, _)) => fields.get(i).map(|(_, shape)| shape),
+            (Self::Enum(variants), Segment::Variant(i, _)) => {
+                variants.get(i as usize).map(|(_, shape)| shape)
+            }
+            _ => None,
+        }
+    }
+
+    /// The shapes directly nested in this shape, along with the segments leading to them.
+    fn children(&self) -> Vec<(Segment, &Self)> {
+        match self {
+            Self::Primitive(_) | Self::Unit => Vec::new(),
+            Self::Override(_, inner) => inner.children(),
+            Self::Option(inner) => vec![(Segment::Some, inner)],
+            Self::Seq(element) => vec![(Segment::Index(0), element)],
+            Self::Tuple(elements) => elements
+                .iter()
+                .enumerate()
+                .map(|(i, shape)| (Segment::Index(i), shape))
+                .collect(),
+            Self::Struct(fields) => fields
+                .iter()
+                .enumerate()
+                .map(|(i, (name, shape))| (Segment::Field(i, name), shape))
+                .collect(),
+            Self::Enum(variants) => variants
+                .iter()
+                .enumerate()
+                .map(|(i, (name, shape))| (Segment::Variant(i as u32, name), shape))
+                .collect(),
+        }
+    }
+
+    /// The path to the first variant that isn't in `explored`.
+    fn unexplored(
+        &self,
+        explored: &HashSet<Vec<Segment>>,
+        path: &mut Vec<Segment>,
+    ) -> Option<Vec<Segment>> {
+        for (segment, child) in self.children() {
+            path.push(segment);
+            if matches!(segment, Segment::Variant(..)) && !explored.contains(path) {
+                return Some(path.clone());
+            }
+            if let Some(variant) = child.unexplored(explored, path) {
+                return Some(variant);
+            }
+            path.pop();
+        }
+        None
+    }
+
+    /// Merges the variants that were `picked` while tracing `traced` into this shape.
+    fn merge(
+        &mut self,
+        traced: Self,
+        explored: &HashSet<Vec<Segment>>,
+        picked: &HashSet<Vec<Segment>>,
+        path: &mut Vec<Segment>,
+    ) {
+        let pairs: Vec<(Segment, &mut Self, Self)> = match (self, traced) {
+            (Self::Override(_, inner), Self::Override(_, traced)) => {
+                return inner.merge(*traced, explored, picked, path);
+            }
+            (Self::Option(inner), Self::Option(traced)) => vec![(Segment::Some, inner, *traced)],
+            (Self::Seq(element), Self::Seq(traced)) => {
+                vec![(Segment::Index(0), element, *traced)]
+            }
+            (Self::Tuple(elements), Self::Tuple(traced)) => elements
+                .iter_mut()
+                .zip(traced)
+                .enumerate()
+                .map(|(i, (shape, traced))| (Segment::Index(i), shape, traced))
+                .collect(),
+            (Self::Struct(fields), Self::Struct(traced)) => fields
+                .iter_mut()
+                .zip(traced)
+                .enumerate()
+                .map(|(i, ((name, shape), (_, traced)))| (Segment::Field(i, name), shape, traced))
+                .collect(),
+            (Self::Enum(variants), Self::Enum(traced)) => variants
+                .iter_mut()
+                .zip(traced)
+                .enumerate()
+                .map(|(i, ((name, shape), (_, traced)))| {
+                    (Segment::Variant(i as u32, name), shape, traced)
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        for (segment, shape, traced) in pairs {
+            path.push(segment);
+            let is_variant = matches!(segment, Segment::Variant(..));
+            if !is_variant || (picked.contains(path) && explored.contains(path)) {
+                shape.merge(traced, explored, picked, path);
+            } else if picked.contains(path) {
+                *shape = traced;
+            }
+            path.pop();
+        }
+    }
+
+    /// The number of values this shape occupies, or `None` if it depends on the value.
+    pub(crate) fn width(&self, config: &Config) -> Option<Width> {
+        match self {
+            Self::Primitive(primitive) => Some(primitive.width(config)),
+            Self::Unit => Some(Width::default()),
+            Self::Option(inner) => {
+                let flag = Width { f: 0, i: 0, b: 1 };
+                Some(flag + inner.width(config)?)
+            }
+            Self::Seq(element) => {
+                let len = Width { f: 0, i: 1, b: 0 };
+                Some(len + element.width(config)? * config.max_seq_len?)
+            }
+            Self::Tuple(elements) => elements.iter().map(|shape| shape.width(config)).sum(),
+            Self::Struct(fields) => fields.iter().map(|(_, shape)| shape.width(config)).sum(),
+            Self::Enum(variants) => {
+                Some(discriminant_width(variants.len(), config) + self.union_width(config)?)
+            }
+            Self::Override(Override::Variants(variants), inner) => inner.width(&Config {
+                variants: *variants,
+                ..*config
+            }),
+            Self::Override(Override::Lane(lane), inner) => {
+                Some(inner.width(config)?.in_lane(*lane))
+            }
+        }
+    }
+
+    /// The width of a whole encoded value of this shape, whose values are all in one lane if
+    /// [`Config::all_floats`] is set.
+    pub(crate) fn encoded_width(&self, config: &Config) -> Option<Width> {
+        let width = self.width(config)?;
+        Some(
+            config
+                .single_lane()
+                .map_or(width, |lane| width.in_lane(lane)),
+        )
+    }
+
+    /// The width of the region shared by the payloads of an enum's variants, which is as wide
+    /// as the widest payload in each lane.
+    pub(crate) fn union_width(&self, config: &Config) -> Option<Width> {
+        match self {
+            Self::Enum(variants) => variants
+                .iter()
+                .try_fold(Width::default(), |union, (_, shape)| {
+                    Some(union.max(shape.width(config)?))
+                }),
+            _ => None,
+        }
+    }
+
+    /// The padding that follows the payload of an enum's variant to fill the shared region.
+    pub(crate) fn variant_padding(&self, variant_index: u32, config: &Config) -> Option<Width> {
+        let payload = self
+            .child(Segment::Variant(variant_index, ""))?
+            .width(config)?;
+        Some(self.union_width(config)? - payload)
+    }
+}
+
+/// The number of values used to store which of `variants` variants an enum holds.
+pub(crate) fn discriminant_width(variants: usize, config: &Config) -> Width {
+    match config.variants {
+        VariantRepr::Index => Width { f: 0, i: 1, b: 0 },
+        VariantRepr::OneHot => Width {
+            f: 0,
+            i: 0,
+            b: variants,
+        },
+        VariantRepr::OneHotFloat => Width {
+            f: variants,
+            i: 0,
+            b: 0,
+        },
+    }
+}
+
+impl Primitive {
+    /// The name of the primitive's Rust type.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Bool => "bool",
+            Self::I8 => "i8",
+            Self::I16 => "i16",
+            Self::I32 => "i32",
+            Self::I64 => "i64",
+            Self::I128 => "i128",
+            Self::U8 => "u8",
+            Self::U16 => "u16",
+            Self::U32 => "u32",
+            Self::U64 => "u64",
+            Self::U128 => "u128",
+            Self::F32 => "f32",
+            Self::F64 => "f64",
+        }
+    }
+
+    pub(crate) fn width(self, config: &Config) -> Width {
+        let i = match (self, config.wide_ints) {
+            (Self::U64 | Self::I128 | Self::U128, WideIntPolicy::Split) => 2,
+            _ => 1,
+        };
+        match self {
+            Self::Bool => Width { f: 0, i: 0, b: 1 },
+            Self::F32 | Self::F64 => Width { f: 1, i: 0, b: 0 },
+            _ => Width { f: 0, i, b: 0 },
+        }
+    }
+}
+
+impl Add for Width {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            f: self.f + rhs.f,
+            i: self.i + rhs.i,
+            b: self.b + rhs.b,
+        }
+    }
+}
+
+impl AddAssign for Width {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Width {
+    /// The width of the same values when they are all stored in `lane`.
+    pub(crate) fn in_lane(self, lane: Lane) -> Self {
+        let total = self.f + self.i + self.b;
+        match lane {
+            Lane::F => Self {
+                f: total,
+                i: 0,
+                b: 0,
+            },
+            Lane::I => Self {
+                f: 0,
+                i: total,
+                b: 0,
+            },
+            Lane::B => Self {
+                f: 0,
+                i: 0,
+                b: total,
+            },
+        }
+    }
+
+    /// The larger of each lane's widths.
+    pub(crate) fn max(self, other: Self) -> Self {
+        Self {
+            f: self.f.max(other.f),
+            i: self.i.max(other.i),
+            b: self.b.max(other.b),
+        }
+    }
+}
+
+impl Sub for Width {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self {
+            f: self.f - rhs.f,
+            i: self.i - rhs.i,
+            b: self.b - rhs.b,
+        }
+    }
+}
+
+impl Mul<usize> for Width {
+    type Output = Self;
+
+    fn mul(self, rhs: usize) -> Self::Output {
+        Self {
+            f: self.f * rhs,
+            i: self.i * rhs,
+            b: self.b * rhs,
+        }
+    }
+}
+
+impl std::iter::Sum for Width {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+/// A deserializer that produces zeroed values while recording the shape of what is being
+/// deserialized.
+#[derive(Default)]
+struct Tracer {
+    shapes: Vec<Shape>,
+    path: Vec<Segment>,
+    /// The variant to pick for the enum at a path. The first variant is picked otherwise.
+    choices: HashMap<Vec<Segment>, u32>,
+    /// The paths to the variants that were picked.
+    picked: HashSet<Vec<Segment>>,
+    /// The paths to the primitives that rejected zero, which are probed with one instead.
+    probes: HashSet<Vec<Segment>>,
+    /// The path to the primitive that rejected its probe in the current pass, if any.
+    rejected: Option<Vec<Segment>>,
+}
+
+impl Tracer {
+    /// Traces `T`, returning its shape and the variants that were picked along the way. The
+    /// payloads of the variants that weren't picked are traced as [`Shape::Unit`].
+    ///
+    /// `T` is traced again every time one of its primitives rejects zero, until every such
+    /// primitive is probed with one. The rejections are kept in `probes` for later passes.
+    fn run<'de, T>(
+        choices: HashMap<Vec<Segment>, u32>,
+        probes: &mut HashSet<Vec<Segment>>,
+    ) -> Result<(Shape, HashSet<Vec<Segment>>)>
+    where
+        T: Deserialize<'de>,
+    {
+        let mut tracer = Self {
+            choices,
+            probes: std::mem::take(probes),
+            ..Default::default()
+        };
+        let traced = loop {
+            match T::deserialize(&mut tracer) {
+                Err(_) if tracer.retry() => {}
+                traced => break traced,
+            }
+        };
+        *probes = std::mem::take(&mut tracer.probes);
+        traced?;
+        let shape = tracer
+            .shapes
+            .pop()
+            .ok_or_else(|| <Error as serde::de::Error>::custom("the type has no shape"))?;
+        Ok((shape, tracer.picked))
+    }
+
+    /// Prepares another pass if a primitive rejected zero in this one, returning whether there
+    /// is one.
+    fn retry(&mut self) -> bool {
+        let Some(path) = self.rejected.take() else {
+            return false;
+        };
+        if !self.probes.insert(path) {
+            return false;
+        }
+        self.shapes.clear();
+        self.path.clear();
+        self.picked.clear();
+        true
+    }
+
+    fn primitive<T>(&mut self, primitive: Primitive, value: T) -> T {
+        self.shapes.push(Shape::Primitive(primitive));
+        value
+    }
+
+    /// Runs `f` and collects the shapes it traced.
+    fn children<F, T>(&mut self, f: F) -> Result<(T, Vec<Shape>)>
+    where
+        F: FnOnce(&mut Self) -> Result<T>,
+    {
+        if self.path.len() >= MAX_DEPTH {
+            return Err(serde::de::Error::custom(
+                "the type is nested too deeply, it might be recursive",
+            ));
+        }
+        let start = self.shapes.len();
+        let value = f(self)?;
+        Ok((value, self.shapes.split_off(start)))
+    }
+
+    /// Runs `f` on the child found by following `segment`, and collects its shape.
+    fn child<F, T>(&mut self, segment: Segment, f: F) -> Result<(T, Shape)>
+    where
+        F: FnOnce(&mut Self) -> Result<T>,
+    {
+        self.path.push(segment);
+        let (value, mut shapes) = self.children(f)?;
+        self.path.pop();
+        let shape = shapes
+            .pop()
+            .ok_or_else(|| <Error as serde::de::Error>::custom("the value has no shape"))?;
+        Ok((value, shape))
+    }
+}
+
+macro_rules! trace_primitive {
+    ($deserialize:ident, $visit:ident, $primitive:ident, $zero:expr, $one:expr) => {
+        fn $deserialize<V>(self, visitor: V) -> Result<V::Value>
+        where
+            V: Visitor<'de>,
+        {
+            let probe = if self.probes.contains(&self.path) {
+                $one
+            } else {
+                $zero
+            };
+            visitor
+                .$visit(self.primitive(Primitive::$primitive, probe))
+                .inspect_err(|_| self.rejected = Some(self.path.clone()))
+        }
+    };
+}
+
+macro_rules! trace_unsupported {
+    ($($deserialize:ident, $kind:literal;)*) => {
+        $(
+            fn $deserialize<V>(self, _visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                Err(Error::Unsupported {
+                    kind: $kind,
+                    path: display_path(&self.path),
+                })
+            }
+        )*
+    };
+}
+
+impl<'de> serde::de::Deserializer<'de> for &mut Tracer {
+    type Error = Error;
+
+    trace_primitive!(deserialize_bool, visit_bool, Bool, false, true);
+    trace_primitive!(deserialize_i8, visit_i8, I8, 0, 1);
+    trace_primitive!(deserialize_i16, visit_i16, I16, 0, 1);
+    trace_primitive!(deserialize_i32, visit_i32, I32, 0, 1);
+    trace_primitive!(deserialize_i64, visit_i64, I64, 0, 1);
+    trace_primitive!(deserialize_i128, visit_i128, I128, 0, 1);
+    trace_primitive!(deserialize_u8, visit_u8, U8, 0, 1);
+    trace_primitive!(deserialize_u16, visit_u16, U16, 0, 1);
+    trace_primitive!(deserialize_u32, visit_u32, U32, 0, 1);
+    trace_primitive!(deserialize_u64, visit_u64, U64, 0, 1);
+    trace_primitive!(deserialize_u128, visit_u128, U128, 0, 1);
+    trace_primitive!(deserialize_f32, visit_f32, F32, 0.0, 1.0);
+    trace_primitive!(deserialize_f64, visit_f64, F64, 0.0, 1.0);
+
+    trace_unsupported!(
+        deserialize_any, "any";
+        deserialize_char, "char";
+        deserialize_str, "str";
+        deserialize_string, "string";
+        deserialize_bytes, "bytes";
+        deserialize_byte_buf, "byte buf";
+        deserialize_map, "map";
+        deserialize_identifier, "identifier";
+        deserialize_ignored_any, "ignored any";
+    );
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let (value, inner) = self.child(Segment::Some, |tracer| visitor.visit_some(tracer))?;
+        self.shapes.push(Shape::Option(Box::new(inner)));
+        Ok(value)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let (value, mut element) =
+            self.children(|tracer| visitor.visit_seq(Elements::new(tracer, 1, None)))?;
+        let element = element
+            .pop()
+            .ok_or_else(|| <Error as serde::de::Error>::custom("the sequence has no shape"))?;
+        self.shapes.push(Shape::Seq(Box::new(element)));
+        Ok(value)
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.shapes.push(Shape::Unit);
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let Some(with) = Override::from_name(name) else {
+            return visitor.visit_newtype_struct(self);
+        };
+        let (value, mut inner) = self.children(|tracer| visitor.visit_newtype_struct(tracer))?;
+        let inner = inner
+            .pop()
+            .ok_or_else(|| <Error as serde::de::Error>::custom("the newtype has no shape"))?;
+        self.shapes.push(Shape::Override(with, Box::new(inner)));
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let (value, elements) =
+            self.children(|tracer| visitor.visit_seq(Elements::new(tracer, len, None)))?;
+        self.shapes.push(Shape::Tuple(elements));
+        Ok(value)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let (value, shapes) = self.children(|tracer| {
+            visitor.visit_seq(Elements::new(tracer, fields.len(), Some(fields)))
+        })?;
+        self.shapes
+            .push(Shape::Struct(fields.iter().copied().zip(shapes).collect()));
+        Ok(value)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let index = self
+            .choices
+            .get(&self.path)
+            .copied()
+            .filter(|index| (*index as usize) < variants.len())
+            .unwrap_or(0);
+        let (value, mut payload) = self.children(|tracer| {
+            visitor.visit_enum(TracedVariant {
+                tracer,
+                index,
+                name: variants[index as usize],
+            })
+        })?;
+        let payload = payload
+            .pop()
+            .ok_or_else(|| <Error as serde::de::Error>::custom("the variant has no shape"))?;
+
+        let mut payload = Some(payload);
+        let variants = variants
+            .iter()
+            .enumerate()
+            .map(|(i, name)| match payload.take_if(|_| i == index as usize) {
+                Some(payload) => (*name, payload),
+                None => (*name, Shape::Unit),
+            })
+            .collect();
+        self.shapes.push(Shape::Enum(variants));
+        Ok(value)
+    }
+}
+
+/// The variant picked by the tracer.
+struct TracedVariant<'a> {
+    tracer: &'a mut Tracer,
+    index: u32,
+    name: &'static str,
+}
+
+impl TracedVariant<'_> {
+    /// Runs `f` on the variant's payload, recording that the variant was picked.
+    fn payload<F, T>(self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Tracer) -> Result<T>,
+    {
+        let tracer = self.tracer;
+        tracer.path.push(Segment::Variant(self.index, self.name));
+        tracer.picked.insert(tracer.path.clone());
+        let value = f(tracer)?;
+        tracer.path.pop();
+        Ok(value)
+    }
+}
+
+impl<'de> EnumAccess<'de> for TracedVariant<'_> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(self.index.into_deserializer())?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for TracedVariant<'_> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        self.payload(|tracer| {
+            tracer.shapes.push(Shape::Unit);
+            Ok(())
+        })
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        self.payload(|tracer| seed.deserialize(tracer))
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.payload(|tracer| serde::de::Deserializer::deserialize_tuple(tracer, len, visitor))
+    }
+
+    fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.payload(|tracer| {
+            serde::de::Deserializer::deserialize_struct(tracer, "", fields, visitor)
+        })
+    }
+}
+
+struct Elements<'a> {
+    tracer: &'a mut Tracer,
+    len: usize,
+    i: usize,
+    /// The names of the elements, if they are the fields of a struct.
+    fields: Option<&'static [&'static str]>,
+}
+
+impl<'a> Elements<'a> {
+    fn new(tracer: &'a mut Tracer, len: usize, fields: Option<&'static [&'static str]>) -> Self {
+        Self {
+            tracer,
+            len,
+            i: 0,
+            fields,
+        }
+    }
+}
+
+impl<'de> SeqAccess<'de> for Elements<'_> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if self.i >= self.len {
+            return Ok(None);
+        }
+        self.tracer.path.push(element_segment(self.i, self.fields));
+        let value = seed.deserialize(&mut *self.tracer)?;
+        self.tracer.path.pop();
+        self.i += 1;
+        Ok(Some(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len - self.i)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::{Primitive, Segment, Shape, Width};
+    use crate::config::Config;
+
+    #[test]
+    fn nested_options() {
+        #[derive(Deserialize)]
+        #[allow(dead_code)]
+        struct Bar {
+            a: f64,
+            b: Option<(i64, bool)>,
+        }
+
+        #[derive(Deserialize)]
+        #[allow(dead_code)]
+        struct Foo {
+            a: u8,
+            bar: Option<Bar>,
+        }
+
+        let shape = Shape::trace::<Foo>().unwrap();
+        let bar = Shape::Struct(vec![
+            ("a", Shape::Primitive(Primitive::F64)),
+            (
+                "b",
+                Shape::Option(Box::new(Shape::Tuple(vec![
+                    Shape::Primitive(Primitive::I64),
+                    Shape::Primitive(Primitive::Bool),
+                ]))),
+            ),
+        ]);
+        assert_eq!(
+            shape,
+            Shape::Struct(vec![
+                ("a", Shape::Primitive(Primitive::U8)),
+                ("bar", Shape::Option(Box::new(bar.clone()))),
+            ])
+        );
+
+        assert_eq!(
+            shape.at(&[Segment::Field(1, "bar"), Segment::Some]),
+            Some(&bar)
+        );
+        assert_eq!(
+            shape.width(&Config::default()),
+            Some(Width { f: 1, i: 2, b: 3 })
+        );
+    }
+
+    #[test]
+    fn nested_variants() {
+        #[derive(Deserialize)]
+        #[allow(dead_code)]
+        enum Inner {
+            X(f64),
+            Y { a: i64, b: bool },
+        }
+
+        #[derive(Deserialize)]
+        #[allow(dead_code)]
+        enum Outer {
+            A(Inner),
+            B,
+            C(Inner, f64),
+        }
+
+        let inner = Shape::Enum(vec![
+            ("X", Shape::Primitive(Primitive::F64)),
+            (
+                "Y",
+                Shape::Struct(vec![
+                    ("a", Shape::Primitive(Primitive::I64)),
+                    ("b", Shape::Primitive(Primitive::Bool)),
+                ]),
+            ),
+        ]);
+        let outer = Shape::Enum(vec![
+            ("A", inner.clone()),
+            ("B", Shape::Unit),
+            (
+                "C",
+                Shape::Tuple(vec![inner, Shape::Primitive(Primitive::F64)]),
+            ),
+        ]);
+        assert_eq!(Shape::trace::<Outer>().unwrap(), outer);
+
+        // discriminant, then a region as wide as `C`
+        assert_eq!(
+            outer.width(&Config::default()),
+            Some(Width { f: 2, i: 3, b: 1 })
+        );
+        assert_eq!(
+            outer.variant_padding(0, &Config::default()),
+            Some(Width { f: 1, i: 0, b: 0 })
+        );
+    }
+}
@@ -0,0 +1,388 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{
+    config::Config,
+    decode_padded_with_config,
+    error::{Error, Result},
+    layout::Layout,
+    serializer::{Output, Rejected, Serializer},
+    Encoding, Lane,
+};
+
+/// A single value of an encoding, tagged with the lane it belongs to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    F(f64),
+    I(i64),
+    B(bool),
+}
+
+impl Value {
+    /// The lane this value is stored in when it's part of an [`Encoding`].
+    pub fn lane(self) -> Lane {
+        match self {
+            Self::F(_) => Lane::F,
+            Self::I(_) => Lane::I,
+            Self::B(_) => Lane::B,
+        }
+    }
+}
+
+/// The values of an encoding in the order they were written, which is the order of the fields
+/// they come from, instead of split into three lanes. Sequence models can take one value per
+/// field from it.
+///
+/// Splitting a stream by lane gives the [`Encoding`] of the same value. Going the other way
+/// needs the type, since the order of the values isn't recorded in an encoding.
+///
+/// Example:
+/// ```rust
+/// use encodable::{
+///     encode_padded,
+///     stream::{decode_stream, encode_stream, Value},
+/// };
+///
+/// let value = (1, Some(2.5), true);
+/// let stream = encode_stream(&value).unwrap();
+/// assert_eq!(
+///     stream.values,
+///     vec![Value::I(1), Value::B(true), Value::F(2.5), Value::B(true)]
+/// );
+/// assert_eq!(stream.to_encoding(), encode_padded(&value).unwrap());
+/// assert_eq!(decode_stream::<(i64, Option<f64>, bool)>(&stream).unwrap(), value);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Stream {
+    pub values: Vec<Value>,
+    /// The index in `values` of every value written to the f, i and b lanes by a
+    /// [`Serializer`], so that a length prefix can be filled in without searching for it.
+    positions: [Vec<usize>; 3],
+}
+
+impl Stream {
+    /// Orders the values of `encoding`, an encoding of `T`, as they were written.
+    pub fn from_encoding<'de, T>(encoding: &Encoding) -> Result<Self>
+    where
+        T: Deserialize<'de>,
+    {
+        Self::from_encoding_with_config::<T>(encoding, Config::default())
+    }
+
+    /// Orders the values of `encoding`, an encoding of `T` made using a custom config, as they
+    /// were written. Sequences must have a fixed length, set with [`Config::max_seq_len`].
+    ///
+    /// Every value is put where its [`Leaf`](crate::layout::Leaf) is in the [`Layout`] of `T`,
+    /// and is kept as it is. Padding is put where the fields it stands in for would be, whereas
+    /// [`encode_stream`] writes it lane by lane, so the two can order the padding of an absent
+    /// value differently.
+    pub fn from_encoding_with_config<'de, T>(encoding: &Encoding, config: Config) -> Result<Self>
+    where
+        T: Deserialize<'de>,
+    {
+        let layout = Layout::with_config::<T>(config)?;
+        for (lane, expected, len) in [
+            (Lane::F, layout.f, encoding.f.len()),
+            (Lane::I, layout.i, encoding.i.len()),
+            (Lane::B, layout.b, encoding.b.len()),
+        ] {
+            if expected != len {
+                return Err(Error::LayoutMismatch {
+                    lane,
+                    expected,
+                    len,
+                });
+            }
+        }
+
+        // the leaves of an enum's variants share their offsets, so each value is taken once, by
+        // the first leaf that reaches it, along with any values skipped before it in its lane
+        let mut stream = Self::default();
+        let mut next = [0; 3];
+        let mut take = |stream: &mut Self, lane: Lane, end: usize| {
+            let next = &mut next[lane_index(lane)];
+            for offset in *next..end {
+                stream.values.push(match lane {
+                    Lane::F => Value::F(encoding.f[offset]),
+                    Lane::I => Value::I(encoding.i[offset]),
+                    Lane::B => Value::B(encoding.b[offset]),
+                });
+            }
+            *next = (*next).max(end);
+        };
+        for leaf in &layout.leaves {
+            take(&mut stream, leaf.lane, leaf.offset + 1);
+        }
+        for (lane, len) in [
+            (Lane::F, layout.f),
+            (Lane::I, layout.i),
+            (Lane::B, layout.b),
+        ] {
+            take(&mut stream, lane, len);
+        }
+        Ok(stream)
+    }
+
+    /// Splits the values into the lanes of an [`Encoding`], keeping their order within each
+    /// lane.
+    pub fn to_encoding(&self) -> Encoding {
+        let mut encoding = Encoding::default();
+        for value in &self.values {
+            match *value {
+                Value::F(f) => encoding.f.push(f),
+                Value::I(i) => encoding.i.push(i),
+                Value::B(b) => encoding.b.push(b),
+            }
+        }
+        encoding
+    }
+
+    /// Appends `value`, which is stored in `lane`.
+    fn push(&mut self, lane: Lane, value: Value) {
+        self.positions[lane_index(lane)].push(self.values.len());
+        self.values.push(value);
+    }
+}
+
+impl From<Vec<Value>> for Stream {
+    fn from(values: Vec<Value>) -> Self {
+        Self {
+            values,
+            positions: Default::default(),
+        }
+    }
+}
+
+impl PartialEq for Stream {
+    fn eq(&self, other: &Self) -> bool {
+        self.values == other.values
+    }
+}
+
+/// The index of `lane` in the per-lane arrays of a [`Stream`].
+fn lane_index(lane: Lane) -> usize {
+    match lane {
+        Lane::F => 0,
+        Lane::I => 1,
+        Lane::B => 2,
+    }
+}
+
+impl Output for Stream {
+    fn written(&self, lane: Lane) -> usize {
+        self.positions[lane_index(lane)].len()
+    }
+
+    fn push_f(&mut self, f: f64) -> std::result::Result<(), Rejected> {
+        self.push(Lane::F, Value::F(f));
+        Ok(())
+    }
+
+    fn push_i(&mut self, i: i64) -> std::result::Result<(), Rejected> {
+        self.push(Lane::I, Value::I(i));
+        Ok(())
+    }
+
+    fn push_b(&mut self, b: bool) -> std::result::Result<(), Rejected> {
+        self.push(Lane::B, Value::B(b));
+        Ok(())
+    }
+
+    fn pad(&mut self, lane: Lane, count: usize) -> std::result::Result<(), Rejected> {
+        let zero = match lane {
+            Lane::F => Value::F(0.0),
+            Lane::I => Value::I(0),
+            Lane::B => Value::B(false),
+        };
+        for _ in 0..count {
+            self.push(lane, zero);
+        }
+        Ok(())
+    }
+
+    fn set_len(
+        &mut self,
+        lane: Lane,
+        index: usize,
+        len: usize,
+    ) -> std::result::Result<(), Rejected> {
+        if let Some(&position) = self.positions[lane_index(lane)].get(index) {
+            self.values[position] = match lane {
+                Lane::F => Value::F(len as f64),
+                Lane::I => Value::I(len as i64),
+                Lane::B => Value::B(len != 0),
+            };
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.values.clear();
+        for positions in &mut self.positions {
+            positions.clear();
+        }
+    }
+
+    fn reserve(&mut self, width: [usize; 3]) {
+        self.values.reserve(width.iter().sum());
+        for (positions, additional) in self.positions.iter_mut().zip(width) {
+            positions.reserve(additional);
+        }
+    }
+}
+
+/// Encodes a struct into a [`Stream`] of tagged values, in field order.
+pub fn encode_stream<'de, T>(value: &T) -> Result<Stream>
+where
+    T: Serialize + Deserialize<'de>,
+{
+    encode_stream_with_config(value, Config::default())
+}
+
+/// Encodes a struct into a [`Stream`] of tagged values using a custom config.
+pub fn encode_stream_with_config<'de, T>(value: &T, config: Config) -> Result<Stream>
+where
+    T: Serialize + Deserialize<'de>,
+{
+    let mut serializer = Serializer::default()
+        .with_config(config)
+        .with_output(Stream::default())
+        .with_shape_of::<T>()?;
+    value.serialize(&mut serializer)?;
+    Ok(serializer.into_output())
+}
+
+/// Decodes a struct from a [`Stream`] of tagged values.
+pub fn decode_stream<T>(stream: &Stream) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    decode_stream_with_config(stream, Config::default())
+}
+
+/// Decodes a struct that was encoded using a custom config from a [`Stream`] of tagged values.
+pub fn decode_stream_with_config<T>(stream: &Stream, config: Config) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    decode_padded_with_config(&stream.to_encoding(), config)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::{decode_stream_with_config, encode_stream_with_config, Stream, Value};
+    use crate::{config::Config, encode_padded_with_config, error::Error, Lane};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Circle(f64),
+        Rect { w: u8, h: u8 },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Foo {
+        a: Vec<(i64, bool)>,
+        b: Shape,
+        c: Option<f64>,
+    }
+
+    #[test]
+    fn field_order() {
+        let foo = Foo {
+            a: vec![(1, true)],
+            b: Shape::Circle(0.5),
+            c: None,
+        };
+        let config = Config {
+            max_seq_len: Some(2),
+            ..Default::default()
+        };
+        let stream = encode_stream_with_config(&foo, config).unwrap();
+        assert_eq!(
+            stream.values,
+            vec![
+                // the length prefix is filled in once the sequence ends
+                Value::I(1),
+                Value::I(1),
+                Value::B(true),
+                Value::I(0),
+                Value::B(false),
+                // the variant's payload is padded to the width of the widest one
+                Value::I(0),
+                Value::F(0.5),
+                Value::I(0),
+                Value::I(0),
+                Value::B(false),
+                Value::F(0.0),
+            ]
+        );
+
+        let encoding = encode_padded_with_config(&foo, config).unwrap();
+        assert_eq!(stream.to_encoding(), encoding);
+        assert_eq!(
+            Stream::from_encoding_with_config::<Foo>(&encoding, config).unwrap(),
+            stream
+        );
+        assert_eq!(
+            decode_stream_with_config::<Foo>(&stream, config).unwrap(),
+            foo
+        );
+
+        // with a single lane, the stream is the encoding's only lane
+        let config = Config {
+            all_floats: true,
+            ..config
+        };
+        let stream = encode_stream_with_config(&foo, config).unwrap();
+        let encoding = encode_padded_with_config(&foo, config).unwrap();
+        assert!(stream.values.iter().all(|value| value.lane() == Lane::F));
+        assert_eq!(stream.to_encoding(), encoding);
+    }
+
+    #[test]
+    fn from_encoding_keeps_values() {
+        let config = Config {
+            max_seq_len: Some(2),
+            ..Default::default()
+        };
+        let foo = Foo {
+            a: vec![],
+            b: Shape::Rect { w: 2, h: 3 },
+            c: None,
+        };
+        let mut encoding = encode_padded_with_config(&foo, config).unwrap();
+        // values a decoding ignores, such as those of a model's output, are kept
+        encoding.f[0] = 0.25;
+        encoding.f[1] = 0.75;
+        encoding.i[1] = 5;
+        let stream = Stream::from_encoding_with_config::<Foo>(&encoding, config).unwrap();
+        assert_eq!(
+            stream.values,
+            vec![
+                Value::I(0),
+                Value::I(5),
+                Value::B(false),
+                Value::I(0),
+                Value::B(false),
+                Value::I(1),
+                Value::F(0.25),
+                Value::I(2),
+                Value::I(3),
+                Value::B(false),
+                Value::F(0.75),
+            ]
+        );
+        assert_eq!(stream.to_encoding(), encoding);
+
+        encoding.b.pop();
+        assert!(matches!(
+            Stream::from_encoding_with_config::<Foo>(&encoding, config),
+            Err(Error::LayoutMismatch {
+                lane: Lane::B,
+                expected: 3,
+                len: 2,
+            })
+        ));
+    }
+}